@@ -0,0 +1,216 @@
+//! `#[derive(Updatable)]` for the `shared-state-machine` crate.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, Ident};
+
+/// Derives `Updatable` for a struct with named fields or for an enum.
+///
+/// A struct `Foo` gets an update enum `FooUpdate` with, per field `bar`, a variant `Bar`
+/// carrying a nested update of the field and a variant `SetBar` replacing it, built by
+/// `foo.bar_mut()` and `foo.set_bar(value)`. The trait `FooNested` offers the same methods
+/// on nested accessors, e.g. `map.get_mut(key).bar_mut().push(1)`.
+///
+/// An enum is updated by replacing its value, built by `value.set(new)` or, nested,
+/// by `map.get_mut(key).set(new)` with the trait `FooNested` in scope.
+#[proc_macro_derive(Updatable)]
+pub fn derive_updatable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "`Updatable` can't be derived for generic types",
+        ));
+    }
+    match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => {
+                let fields: Vec<_> = fields
+                    .named
+                    .iter()
+                    .map(|field| (field.ident.clone().unwrap(), field.ty.clone()))
+                    .collect();
+                Ok(expand_struct(&input, &fields))
+            }
+            _ => Err(Error::new_spanned(
+                &input.ident,
+                "`Updatable` can only be derived for structs with named fields",
+            )),
+        },
+        Data::Enum(_) => Ok(expand_enum(&input)),
+        Data::Union(_) => Err(Error::new_spanned(
+            &input.ident,
+            "`Updatable` can't be derived for unions",
+        )),
+    }
+}
+
+/// `foo_bar` as `FooBar`.
+fn camel_case(ident: &Ident) -> Ident {
+    let name = ident.to_string();
+    let camel: String = name
+        .trim_start_matches("r#")
+        .split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect();
+    Ident::new(&camel, Span::call_site())
+}
+
+fn expand_struct(input: &DeriveInput, fields: &[(Ident, syn::Type)]) -> TokenStream2 {
+    let vis = &input.vis;
+    let name = &input.ident;
+    let update = format_ident!("{}Update", name);
+    let nested = format_ident!("{}Nested", name);
+    let krate = quote!(::shared_state_machine);
+    let updatable = quote!(#krate::ucore::updateable::Updatable);
+    let unested = quote!(#krate::ucore::unested::UNested);
+
+    let mut variants = vec![];
+    let mut arms = vec![];
+    let mut accessors = vec![];
+    let mut signatures = vec![];
+    let mut nested_accessors = vec![];
+    for (field, ty) in fields {
+        let nested_variant = camel_case(field);
+        let set_variant = format_ident!("Set{}", nested_variant);
+        let field_mut = format_ident!("{}_mut", field.to_string().trim_start_matches("r#"));
+        let set_field = format_ident!("set_{}", field.to_string().trim_start_matches("r#"));
+
+        variants.push(quote! {
+            #nested_variant(<#ty as #updatable>::Update),
+            #set_variant(#ty)
+        });
+        arms.push(quote! {
+            #update::#nested_variant(update) => #updatable::try_apply_update(&mut self.#field, update),
+            #update::#set_variant(value) => {
+                self.#field = value;
+                Ok(())
+            }
+        });
+        accessors.push(quote! {
+            #vis fn #field_mut(
+                &self,
+            ) -> #unested<#ty, #update, impl FnOnce(<#ty as #updatable>::Update) -> #update> {
+                #unested {
+                    apply_outer: #update::#nested_variant,
+                    inner_type: ::std::marker::PhantomData,
+                }
+            }
+
+            #vis fn #set_field(&self, value: #ty) -> #update {
+                #update::#set_variant(value)
+            }
+        });
+        signatures.push(quote! {
+            fn #field_mut(self) -> #unested<#ty, O, impl FnOnce(<#ty as #updatable>::Update) -> O>;
+            fn #set_field(self, value: #ty) -> O;
+        });
+        nested_accessors.push(quote! {
+            fn #field_mut(self) -> #unested<#ty, O, impl FnOnce(<#ty as #updatable>::Update) -> O> {
+                #unested {
+                    apply_outer: move |update| (self.apply_outer)(#update::#nested_variant(update)),
+                    inner_type: ::std::marker::PhantomData,
+                }
+            }
+
+            fn #set_field(self, value: #ty) -> O {
+                (self.apply_outer)(#update::#set_variant(value))
+            }
+        });
+    }
+
+    let serde = quote!(#krate::serde);
+    let doc = format!("Accessors of the fields of a nested `{}`.", name);
+    quote! {
+        #[derive(#serde::Serialize, #serde::Deserialize)]
+        #[serde(crate = "::shared_state_machine::serde")]
+        #vis enum #update {
+            #(#variants,)*
+        }
+
+        impl #updatable for #name {
+            type Update = #update;
+
+            fn try_apply_update(
+                &mut self,
+                update: Self::Update,
+            ) -> ::std::result::Result<(), #krate::ucore::updateable::UpdateError> {
+                match update {
+                    #(#arms)*
+                }
+            }
+        }
+
+        impl #name {
+            #(#accessors)*
+        }
+
+        #[doc = #doc]
+        #vis trait #nested<O> {
+            #(#signatures)*
+        }
+
+        impl<O, F> #nested<O> for #unested<#name, O, F>
+        where
+            F: FnOnce(#update) -> O,
+        {
+            #(#nested_accessors)*
+        }
+    }
+}
+
+fn expand_enum(input: &DeriveInput) -> TokenStream2 {
+    let vis = &input.vis;
+    let name = &input.ident;
+    let nested = format_ident!("{}Nested", name);
+    let krate = quote!(::shared_state_machine);
+    let unested = quote!(#krate::ucore::unested::UNested);
+    let doc = format!("Replacement of a nested `{}`.", name);
+    quote! {
+        impl #krate::ucore::updateable::Updatable for #name {
+            type Update = #name;
+
+            fn try_apply_update(
+                &mut self,
+                update: Self::Update,
+            ) -> ::std::result::Result<(), #krate::ucore::updateable::UpdateError> {
+                *self = update;
+                Ok(())
+            }
+        }
+
+        impl #name {
+            #vis fn set(&self, value: #name) -> #name {
+                value
+            }
+        }
+
+        #[doc = #doc]
+        #vis trait #nested<O> {
+            fn set(self, value: #name) -> O;
+        }
+
+        impl<O, F> #nested<O> for #unested<#name, O, F>
+        where
+            F: FnOnce(#name) -> O,
+        {
+            fn set(self, value: #name) -> O {
+                (self.apply_outer)(value)
+            }
+        }
+    }
+}
@@ -0,0 +1,517 @@
+use crate::communication::messages::{ClientMessage, ServerMessage};
+use crate::communication::replica::Replica;
+use crate::communication::subscription::{Subscribe, SubscriptionId, Watch};
+use crate::communication::synchronizer::{
+    applied_after, connection_lost, contended, deadline_passed, rejected, timed_out,
+    to_connection_error, to_disconnected_error, to_lock_error, to_serialization_error,
+    unexpected_response, update_message, ClientConfig, ConnectionStatus, Delivery, MessageHandler,
+    ResponseType, Result, RetryPolicy, SError, UpdateFailure,
+};
+use crate::communication::umessage::{UMessage, USnapshot};
+use crate::ucore::updateable;
+use futures::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::task::JoinHandle;
+use tokio_serde::{formats::*, SymmetricallyFramed};
+use tokio_util::codec::{FramedRead, FramedWrite, LengthDelimitedCodec};
+use updateable::Updatable;
+
+type Deserializer = SymmetricallyFramed<
+    FramedRead<OwnedReadHalf, LengthDelimitedCodec>,
+    ServerMessage,
+    SymmetricalJson<ServerMessage>,
+>;
+
+type Serializer = SymmetricallyFramed<
+    FramedWrite<OwnedWriteHalf, LengthDelimitedCodec>,
+    ClientMessage,
+    SymmetricalJson<ClientMessage>,
+>;
+
+/// Publication of a nested update, returned by the `get_mut`-style accessors.
+pub type PublishFuture<'a> = Pin<Box<dyn Future<Output = Result<Delivery>> + Send + 'a>>;
+
+/// Async counterpart of `Synchronizer`.
+/// Server messages are applied by a task on the caller's runtime instead of dedicated threads.
+/// A lost connection is re-established as the config's reconnect policy allows,
+/// resuming from the last applied update; requests fail while it's down.
+/// Dropping a pending call cancels it, though a write already sent may still be applied.
+pub struct AsyncSynchronizer<T>
+where
+    T: Updatable,
+{
+    shared: Arc<AsyncShared<T>>,
+    group_id: u32,
+    /// Writing half of the connection, `None` while it's down.
+    writer: Option<Serializer>,
+    /// Writers of re-established connections from the reader task, or `None` once one is lost.
+    writers: UnboundedReceiver<Option<Serializer>>,
+    responses: UnboundedReceiver<ResponseType>,
+    /// Requests sent and not answered yet, including cancelled ones.
+    outstanding: u32,
+    retry_policy: RetryPolicy,
+    timeout: Option<Duration>,
+    reader_task: JoinHandle<()>,
+}
+
+struct AsyncShared<T: Updatable> {
+    replica: Replica<T>,
+    status: Mutex<ConnectionStatus>,
+    /// Error that made the server's messages unusable, ending the connection.
+    failure: Mutex<Option<SError>>,
+}
+
+impl<T: Updatable> AsyncShared<T> {
+    /// Error of a request whose connection was lost.
+    fn lost(&self) -> SError {
+        self.failure.lock().map_or_else(to_lock_error, |failure| {
+            failure.clone().unwrap_or_else(connection_lost)
+        })
+    }
+
+    fn set_status(&self, status: ConnectionStatus) {
+        if let Ok(mut current) = self.status.lock() {
+            *current = status;
+        }
+    }
+}
+
+impl<T> AsyncShared<T>
+where
+    T: Updatable + Clone + Default + Serialize + for<'de> Deserialize<'de> + Send + 'static,
+    <T as Updatable>::Update: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+{
+    /// Applies the server's messages until the connection ends,
+    /// returning the error that made them unusable, if any.
+    async fn process_messages(
+        &self,
+        reader: &mut Deserializer,
+        response_sender: &UnboundedSender<ResponseType>,
+    ) -> Option<SError> {
+        let mut handler = MessageHandler::new();
+        loop {
+            let message = match reader.try_next().await {
+                Ok(Some(message)) => message,
+                Ok(None) => return None,
+                // Undecodable frames, as opposed to a lost connection.
+                Err(error) if error.kind() == io::ErrorKind::InvalidData => {
+                    return Some(to_serialization_error(error))
+                }
+                Err(_) => return None,
+            };
+            match handler.handle(message, &self.replica) {
+                Ok(Some(response)) => {
+                    let _ = response_sender.send(response);
+                }
+                Ok(None) => {}
+                Err(error) => return Some(error),
+            }
+        }
+    }
+
+    /// Re-establishes the connection, resuming from the last applied update.
+    /// Returns `None` once the policy gives up.
+    async fn reconnect(
+        &self,
+        config: &ClientConfig,
+        group: u32,
+    ) -> Option<(Deserializer, Serializer)> {
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            tokio::time::sleep(config.policy().delay(attempts)?).await;
+
+            let resume_from = self.replica.last_packet_number();
+            let (reader, writer, join_response) =
+                match join(config.addresses(), group, Some(resume_from)).await {
+                    Ok(connection) => connection,
+                    Err(_) => continue,
+                };
+            if let ServerMessage::OffsetUnavailable = join_response {
+                self.replica.reset();
+            }
+            return Some((reader, writer));
+        }
+    }
+}
+
+/// Connects to the server and joins `group`, resuming from `resume_from` if given.
+/// Returns both halves of the connection and the server's join response.
+async fn join(
+    addresses: &[SocketAddr],
+    group: u32,
+    resume_from: Option<u32>,
+) -> Result<(Deserializer, Serializer, ServerMessage)> {
+    let tcp_stream = TcpStream::connect(addresses)
+        .await
+        .map_err(to_connection_error)?;
+    let (reader, writer) = tcp_stream.into_split();
+    let mut reader: Deserializer = SymmetricallyFramed::new(
+        FramedRead::new(reader, LengthDelimitedCodec::new()),
+        SymmetricalJson::default(),
+    );
+    let mut writer: Serializer = SymmetricallyFramed::new(
+        FramedWrite::new(writer, LengthDelimitedCodec::new()),
+        SymmetricalJson::default(),
+    );
+
+    writer
+        .send(ClientMessage::JoinGroup(group, resume_from))
+        .await
+        .map_err(to_connection_error)?;
+    match reader.try_next().await.map_err(to_connection_error)? {
+        Some(message @ (ServerMessage::Correct | ServerMessage::OffsetUnavailable)) => {
+            Ok((reader, writer, message))
+        }
+        _ => Err(SError::ProtocolError(
+            "Server didn't accept join request".to_owned(),
+        )),
+    }
+}
+
+impl<T> AsyncSynchronizer<T>
+where
+    T: Updatable + Clone + Default + Serialize + for<'de> Deserialize<'de> + Send + 'static,
+    <T as Updatable>::Update: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+{
+    pub async fn new(port: u16, group: u32) -> Result<Self> {
+        Self::with_config(ClientConfig::local(port), group).await
+    }
+
+    pub async fn with_config(config: ClientConfig, group: u32) -> Result<Self> {
+        let (reader, writer, _) = join(config.addresses(), group, None).await?;
+        let shared = Arc::new(AsyncShared {
+            replica: Replica::new(),
+            status: Mutex::new(ConnectionStatus::Connected),
+            failure: Mutex::new(None),
+        });
+        let (response_sender, responses) = mpsc::unbounded_channel();
+        let (writer_sender, writers) = mpsc::unbounded_channel();
+        let reader_task = {
+            let shared = shared.clone();
+            tokio::spawn(async move {
+                let mut reader = reader;
+                loop {
+                    let failure = shared.process_messages(&mut reader, &response_sender).await;
+                    let _ = writer_sender.send(None);
+                    // Reconnecting would resume from the same offset and fail again.
+                    let failed = failure.is_some();
+                    if let Ok(mut current) = shared.failure.lock() {
+                        *current = failure;
+                    }
+                    shared.replica.interrupt();
+                    if failed {
+                        shared.set_status(ConnectionStatus::Failed);
+                        let _ = response_sender.send(ResponseType::Disconnected);
+                        break;
+                    }
+                    let _ = response_sender.send(ResponseType::Disconnected);
+                    shared.set_status(ConnectionStatus::Reconnecting);
+                    match shared.reconnect(&config, group).await {
+                        Some((new_reader, new_writer)) => {
+                            reader = new_reader;
+                            let _ = writer_sender.send(Some(new_writer));
+                            shared.set_status(ConnectionStatus::Connected);
+                        }
+                        None => {
+                            shared.set_status(ConnectionStatus::Failed);
+                            break;
+                        }
+                    }
+                }
+            })
+        };
+
+        Ok(AsyncSynchronizer {
+            shared,
+            group_id: group,
+            writer: Some(writer),
+            writers,
+            responses,
+            outstanding: 0,
+            retry_policy: RetryPolicy::default(),
+            timeout: None,
+            reader_task,
+        })
+    }
+
+    /// Retries writes rejected as stale according to `policy` instead of the default one.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Fails requests to the server with a `TimeoutError` once they take longer than `timeout`.
+    /// Wrap a single call in `tokio::time::timeout` to limit just that one.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Time limit of requests to the server; unlimited by default.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Publishes `update`, retrying as the retry policy allows until the server accepts it.
+    /// Resolves once the update was applied locally, so `get_lock` observes it.
+    pub async fn publish_update(&mut self, update: T::Update) -> Result<Delivery> {
+        self.publish_updates(vec![update]).await
+    }
+
+    /// Publishes `updates` as one packet, which the server accepts or rejects as a whole
+    /// and every replica applies atomically.
+    pub async fn publish_transaction(&mut self, updates: Vec<T::Update>) -> Result<Delivery> {
+        if updates.is_empty() {
+            return Err(SError::InternalError("Empty transaction".to_owned()));
+        }
+        self.publish_updates(updates).await
+    }
+
+    /// Publishes the update computed by `f` from the local state, recomputing it against the
+    /// caught-up state whenever the server rejects it as stale, as the retry policy allows.
+    /// Resolves to `None` without publishing if `f` returns `None`.
+    pub async fn update_with<F>(&mut self, mut f: F) -> Result<Option<Delivery>>
+    where
+        F: FnMut(&T) -> Option<T::Update>,
+    {
+        let group_id = self.group_id;
+        self.publish_with(|packet_id, state| {
+            f(state)
+                .map(|update| UMessage::new(group_id, packet_id, &update))
+                .transpose()
+                .map_err(to_serialization_error)
+        })
+        .await
+        .map(|packet_id| packet_id.map(Delivery::Accepted))
+    }
+
+    async fn publish_updates(&mut self, updates: Vec<T::Update>) -> Result<Delivery> {
+        let group_id = self.group_id;
+        self.publish_with(move |packet_id, _| {
+            update_message(group_id, packet_id, &updates).map(Some)
+        })
+        .await?
+        .map(Delivery::Accepted)
+        .ok_or_else(|| SError::InternalError("No update to publish".to_owned()))
+    }
+
+    /// Async counterpart of `Synchronizer`'s packet publication loop.
+    async fn publish_with<F>(&mut self, message: F) -> Result<Option<u32>>
+    where
+        F: FnMut(u32, &T) -> Result<Option<UMessage>>,
+    {
+        within(self.timeout, self.try_publish_with(message)).await
+    }
+
+    async fn try_publish_with<F>(&mut self, mut message: F) -> Result<Option<u32>>
+    where
+        F: FnMut(u32, &T) -> Result<Option<UMessage>>,
+    {
+        self.discard_stale_responses();
+        let epoch = self.shared.replica.connection_epoch();
+        let policy = self.retry_policy.clone();
+        let mut attempts = 0;
+        loop {
+            let umessage = {
+                let inner = self.shared.replica.lock();
+                match message(self.shared.replica.last_packet_number(), &inner)? {
+                    Some(umessage) => umessage,
+                    None => return Ok(None),
+                }
+            };
+            match self.request(ClientMessage::Update(umessage)).await? {
+                Some(ResponseType::Accepted(packet_id)) => {
+                    return match self
+                        .shared
+                        .replica
+                        .applied_async(applied_after(packet_id)?, epoch)
+                        .await
+                    {
+                        true => match self.shared.replica.take_failure(packet_id) {
+                            Some(error) => Err(SError::ApplyError(error)),
+                            None => Ok(Some(packet_id)),
+                        },
+                        false => Err(self.shared.lost()),
+                    }
+                }
+                Some(ResponseType::Rejected(rejection)) => rejected(rejection)?,
+                Some(ResponseType::Head(_)) => return Err(unexpected_response()),
+                Some(ResponseType::Disconnected) | None => return Err(self.shared.lost()),
+            }
+            attempts += 1;
+            if policy.exhausted(attempts) {
+                return Err(contended(attempts));
+            }
+            tokio::time::sleep(policy.backoff(attempts)).await;
+        }
+    }
+
+    /// Uploads the current state, letting the server drop the history it covers.
+    pub async fn publish_snapshot(&mut self) -> Result<()> {
+        within(self.timeout, self.try_publish_snapshot()).await
+    }
+
+    async fn try_publish_snapshot(&mut self) -> Result<()> {
+        self.discard_stale_responses();
+        let snapshot = {
+            let inner = self.get_lock();
+            let packet_id = self.shared.replica.last_packet_number();
+            USnapshot::new(self.group_id, packet_id, &*inner).map_err(to_serialization_error)?
+        };
+        match self.request(ClientMessage::Snapshot(snapshot)).await? {
+            Some(ResponseType::Accepted(_)) => Ok(()),
+            Some(ResponseType::Rejected(rejection)) => Err(SError::RejectedError(rejection.reason)),
+            Some(ResponseType::Head(_)) => Err(unexpected_response()),
+            Some(ResponseType::Disconnected) | None => Err(self.shared.lost()),
+        }
+    }
+
+    /// Resolves once every update the server had accepted at call time was applied locally.
+    pub async fn sync(&mut self) -> Result<()> {
+        within(self.timeout, self.try_sync()).await
+    }
+
+    async fn try_sync(&mut self) -> Result<()> {
+        self.discard_stale_responses();
+        let epoch = self.shared.replica.connection_epoch();
+        let head = match self.request(ClientMessage::Head).await? {
+            Some(ResponseType::Head(head)) => head,
+            Some(ResponseType::Disconnected) | None => return Err(self.shared.lost()),
+            Some(_) => return Err(unexpected_response()),
+        };
+        match self.shared.replica.applied_async(head, epoch).await {
+            true => Ok(()),
+            false => Err(self.shared.lost()),
+        }
+    }
+
+    /// Waits for at most `timeout` until packet `packet_id` was applied locally.
+    pub async fn wait_for(&self, packet_id: u32, timeout: Duration) -> Result<()> {
+        let epoch = self.shared.replica.connection_epoch();
+        if self.status() == ConnectionStatus::Failed && self.version() <= packet_id {
+            return Err(self.shared.lost());
+        }
+        let applied_up_to = applied_after(packet_id)?;
+        let applied = self.shared.replica.applied_async(applied_up_to, epoch);
+        match tokio::time::timeout(timeout, applied).await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(self.shared.lost()),
+            Err(_) => Err(timed_out(packet_id)),
+        }
+    }
+
+    /// Number of updates applied to the local state; packet `n` is applied once it exceeds `n`.
+    pub fn version(&self) -> u32 {
+        self.shared.replica.last_packet_number()
+    }
+
+    /// Packets that didn't fit the local state since the last call,
+    /// except the ones already reported to the call that published them.
+    pub fn take_update_failures(&self) -> Vec<UpdateFailure> {
+        self.shared.replica.take_failures()
+    }
+
+    /// Subscribes to the changes applied to the local state.
+    pub fn subscribe(&self) -> Subscribe<'_, T> {
+        Subscribe::new(self.shared.replica.subscriptions_mutex())
+    }
+
+    pub fn unsubscribe(&self, id: SubscriptionId) {
+        self.shared.replica.subscriptions().remove(id);
+    }
+
+    /// Watches the changes touching a subtree of the local state.
+    pub fn watch(&self) -> Watch<'_, T, T> {
+        Watch::new(self.shared.replica.subscriptions_mutex())
+    }
+
+    /// Drops the responses to requests that were cancelled or whose connection was lost.
+    fn discard_stale_responses(&mut self) {
+        while let Ok(response) = self.responses.try_recv() {
+            self.answered(&response);
+        }
+    }
+
+    fn answered(&mut self, response: &ResponseType) {
+        match response {
+            ResponseType::Disconnected => self.outstanding = 0,
+            _ => self.outstanding = self.outstanding.saturating_sub(1),
+        }
+    }
+
+    /// Sends `message` and receives the response to it, skipping the ones to earlier requests.
+    /// Yields `None` once the reader task is gone.
+    async fn request(&mut self, message: ClientMessage) -> Result<Option<ResponseType>> {
+        self.send(message).await?;
+        loop {
+            let response = self.responses.recv().await;
+            if let Some(response) = &response {
+                self.answered(response);
+            }
+            if response.is_none() || self.outstanding == 0 {
+                return Ok(response);
+            }
+        }
+    }
+
+    async fn send(&mut self, message: ClientMessage) -> Result<()> {
+        while let Ok(writer) = self.writers.try_recv() {
+            self.writer = writer;
+        }
+        let writer = match self.writer.as_mut() {
+            Some(writer) => writer,
+            None => return Err(self.shared.lost()),
+        };
+        // Once fed, the message is flushed by a later call even if this one is cancelled.
+        writer.feed(message).await.map_err(to_disconnected_error)?;
+        self.outstanding += 1;
+        writer.flush().await.map_err(to_disconnected_error)
+    }
+
+    pub fn get_lock(&self) -> std::sync::MutexGuard<'_, T> {
+        self.shared.replica.lock()
+    }
+
+    pub fn status(&self) -> ConnectionStatus {
+        self.shared
+            .status
+            .lock()
+            .map_or(ConnectionStatus::Failed, |status| *status)
+    }
+}
+
+impl<T> Drop for AsyncSynchronizer<T>
+where
+    T: Updatable,
+{
+    fn drop(&mut self) {
+        self.reader_task.abort();
+    }
+}
+
+/// Fails `future` with a `TimeoutError` once it takes longer than `timeout`.
+async fn within<R>(
+    timeout: Option<Duration>,
+    future: impl Future<Output = Result<R>>,
+) -> Result<R> {
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, future)
+            .await
+            .unwrap_or_else(|_| Err(deadline_passed())),
+        None => future.await,
+    }
+}
@@ -1,16 +1,61 @@
 use crate::communication::umessage;
 use serde::{Deserialize, Serialize};
-use umessage::UMessage;
+use umessage::{UMessage, USnapshot};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum ServerMessage {
     Update(UMessage),
+    Snapshot(USnapshot),
+    /// Join response.
     Correct,
-    Error,
+    /// The update was accepted as the given packet, or the snapshot at the given packet was stored.
+    Accepted(u32),
+    Rejected(Rejection),
+    /// Response to `ClientMessage::Head`: the group's current packet number.
+    Head(u32),
+    /// Join response when the requested resume offset can't be served, also sent to clients
+    /// that fell behind the compacted history. The full state (snapshot and history) follows,
+    /// replacing the client's state.
+    OffsetUnavailable,
+}
+
+/// Why the server refused a client's update or snapshot.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectionReason {
+    /// The packet id isn't the group's head; retry once caught up with `Rejection::head`.
+    StalePacketId,
+    /// The message or its payload couldn't be parsed.
+    MalformedPayload,
+    /// The client isn't allowed to modify the group.
+    Unauthorized,
+    /// A server-side limit was exceeded.
+    QuotaExceeded,
+    /// The change couldn't be persisted.
+    StorageFailure,
+    /// The snapshot is ahead of the group's head, or doesn't hold the state of a validated
+    /// group at its packet id.
+    InvalidSnapshot,
+    /// The update doesn't fit the state of a validated group.
+    InvalidUpdate,
+    /// The group used up its packet ids and accepts no further updates.
+    PacketIdsExhausted,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Rejection {
+    /// Packet id of the rejected message.
+    pub packet_id: u32,
+    /// The group's current packet number.
+    pub head: u32,
+    pub reason: RejectionReason,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum ClientMessage {
-    JoinGroup(u32),
+    /// Joins a group. `Some(n)` resumes a client that already applied every update below packet `n`.
+    JoinGroup(u32, Option<u32>),
     Update(UMessage),
+    Snapshot(USnapshot),
+    /// Asks for the group's current packet number.
+    Head,
 }
@@ -0,0 +1,132 @@
+use crate::communication::messages::{RejectionReason, ServerMessage};
+use crate::communication::umessage::{UMessage, USnapshot};
+use crate::ucore::updateable::Updatable;
+use serde::de::DeserializeOwned;
+use std::any::Any;
+use std::fmt;
+
+/// Typed state of a group, materialized by the server to validate incoming updates.
+pub(crate) trait MirrorState: Send {
+    /// Applies the updates of `umessage` if they all fit, leaving the state unchanged otherwise.
+    fn apply(&mut self, umessage: &UMessage) -> Result<(), RejectionReason>;
+
+    fn restore(&mut self, snapshot: &USnapshot) -> serde_json::Result<()>;
+
+    /// Whether `other` holds the same state.
+    fn same_as(&self, other: &dyn MirrorState) -> bool;
+
+    fn as_any(&self) -> &dyn Any;
+}
+
+struct TypedState<T>(T);
+
+impl<T> MirrorState for TypedState<T>
+where
+    T: Updatable + Clone + PartialEq + DeserializeOwned + Send + 'static,
+    T::Update: DeserializeOwned,
+{
+    fn apply(&mut self, umessage: &UMessage) -> Result<(), RejectionReason> {
+        let mut updates: Vec<T::Update> = umessage
+            .get_updates()
+            .map_err(|_| RejectionReason::MalformedPayload)?;
+        let invalid = |_| RejectionReason::InvalidUpdate;
+        match updates.len() {
+            1 => self.0.try_apply_update(updates.remove(0)).map_err(invalid),
+            _ => {
+                // A transaction fits only as a whole.
+                let mut state = self.0.clone();
+                for update in updates {
+                    state.try_apply_update(update).map_err(invalid)?;
+                }
+                self.0 = state;
+                Ok(())
+            }
+        }
+    }
+
+    fn restore(&mut self, snapshot: &USnapshot) -> serde_json::Result<()> {
+        self.0 = snapshot.get_state()?;
+        Ok(())
+    }
+
+    fn same_as(&self, other: &dyn MirrorState) -> bool {
+        other
+            .as_any()
+            .downcast_ref::<Self>()
+            .is_some_and(|other| self.0 == other.0)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Builds the empty state of a validated group.
+pub(crate) type MirrorFactory = fn() -> Box<dyn MirrorState>;
+
+pub(crate) fn mirror_factory<T>() -> MirrorFactory
+where
+    T: Updatable + Clone + PartialEq + Default + DeserializeOwned + Send + 'static,
+    T::Update: DeserializeOwned,
+{
+    || Box::new(TypedState(T::default()))
+}
+
+/// Typed copy of a group's state at its head.
+pub(crate) struct Mirror {
+    factory: MirrorFactory,
+    state: Box<dyn MirrorState>,
+}
+
+impl Mirror {
+    pub(crate) fn new(factory: MirrorFactory) -> Self {
+        Self {
+            factory,
+            state: factory(),
+        }
+    }
+
+    pub(crate) fn apply(&mut self, umessage: &UMessage) -> Result<(), RejectionReason> {
+        self.state.apply(umessage)
+    }
+
+    /// Rebuilds the state from a group's snapshot and history. Updates that don't fit are
+    /// skipped, as replicas do with the ones accepted before the group was validated.
+    pub(crate) fn rebuild(
+        &mut self,
+        snapshot: Option<&USnapshot>,
+        history: &[ServerMessage],
+    ) -> serde_json::Result<()> {
+        self.state = (self.factory)();
+        if let Some(snapshot) = snapshot {
+            self.state.restore(snapshot)?;
+        }
+        for message in history {
+            if let ServerMessage::Update(umessage) = message {
+                let _ = self.state.apply(umessage);
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether `snapshot` holds the state rebuilt from the group's snapshot `base`
+    /// and the `history` following it up to the snapshot's packet id.
+    pub(crate) fn matches(
+        &self,
+        snapshot: &USnapshot,
+        base: Option<&USnapshot>,
+        history: &[ServerMessage],
+    ) -> bool {
+        let mut expected = Mirror::new(self.factory);
+        let mut candidate = (self.factory)();
+        expected.rebuild(base, history).is_ok()
+            && candidate.restore(snapshot).is_ok()
+            && candidate.same_as(&*expected.state)
+    }
+}
+
+impl fmt::Debug for Mirror {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mirror").finish_non_exhaustive()
+    }
+}
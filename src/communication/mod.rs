@@ -1,4 +1,9 @@
+pub mod async_synchronizer;
 pub mod messages;
+pub(crate) mod mirror;
+pub(crate) mod replica;
 pub mod server;
+pub mod subscription;
 pub mod synchronizer;
 pub mod umessage;
+pub mod wal;
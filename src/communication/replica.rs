@@ -0,0 +1,176 @@
+use crate::communication::subscription::Subscriptions;
+use crate::communication::synchronizer::UpdateFailure;
+use crate::ucore::updateable::Updatable;
+use crate::ucore::updateable::UpdateError;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::Instant;
+use tokio::sync::Notify;
+
+/// Local copy of a shared structure and the number of updates applied to it.
+/// Blocking and async callers can wait for the copy to reach a packet.
+pub(crate) struct Replica<T: Updatable> {
+    inner: Mutex<T>,
+    last_packet_number: AtomicU32,
+    /// Incremented every time the connection is lost, interrupting waiters.
+    connection_epoch: AtomicU32,
+    applied: Condvar,
+    notify: Notify,
+    subscriptions: Mutex<Subscriptions<T>>,
+    /// Latest packets that didn't fit the state, at most `MAX_FAILURES`.
+    failures: Mutex<VecDeque<UpdateFailure>>,
+}
+
+const MAX_FAILURES: usize = 64;
+
+/// Outcome of waiting for a replica to apply a packet.
+pub(crate) enum Wait {
+    Applied,
+    /// The connection was lost first.
+    Interrupted,
+    TimedOut,
+}
+
+impl<T: Updatable + Default> Replica<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            inner: Mutex::new(T::default()),
+            last_packet_number: AtomicU32::new(0),
+            connection_epoch: AtomicU32::new(0),
+            applied: Condvar::new(),
+            notify: Notify::new(),
+            subscriptions: Mutex::new(Subscriptions::new()),
+            failures: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Drops the local state; the server is about to resend it in full.
+    pub(crate) fn reset(&self) {
+        let mut inner = self.lock();
+        *inner = T::default();
+        self.last_packet_number.store(0, Ordering::Relaxed);
+        drop(inner);
+        self.subscriptions().replaced(0);
+    }
+}
+
+impl<T: Updatable> Replica<T> {
+    pub(crate) fn lock(&self) -> MutexGuard<'_, T> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Packet id of the next update to apply.
+    pub(crate) fn last_packet_number(&self) -> u32 {
+        self.last_packet_number.load(Ordering::Relaxed)
+    }
+
+    /// Records that every update below `packet_number` is in `inner`, whose guard is released.
+    /// Subscribers are notified through `notify` before waiters are woken up.
+    pub(crate) fn applied(
+        &self,
+        inner: MutexGuard<'_, T>,
+        packet_number: u32,
+        notify: impl FnOnce(&mut Subscriptions<T>),
+    ) {
+        self.last_packet_number
+            .store(packet_number, Ordering::Relaxed);
+        drop(inner);
+        notify(&mut self.subscriptions());
+        self.applied.notify_all();
+        self.notify.notify_waiters();
+    }
+
+    pub(crate) fn subscriptions(&self) -> MutexGuard<'_, Subscriptions<T>> {
+        self.subscriptions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(crate) fn subscriptions_mutex(&self) -> &Mutex<Subscriptions<T>> {
+        &self.subscriptions
+    }
+
+    /// Records that an update of packet `packet_id` didn't fit the state.
+    pub(crate) fn failed(&self, packet_id: u32, error: UpdateError) {
+        let mut failures = self.failures.lock().unwrap_or_else(|e| e.into_inner());
+        if failures.len() == MAX_FAILURES {
+            failures.pop_front();
+        }
+        failures.push_back(UpdateFailure { packet_id, error });
+    }
+
+    /// Takes the failure of packet `packet_id`, if it had one.
+    pub(crate) fn take_failure(&self, packet_id: u32) -> Option<UpdateError> {
+        let mut failures = self.failures.lock().unwrap_or_else(|e| e.into_inner());
+        let index = failures
+            .iter()
+            .position(|failure| failure.packet_id == packet_id)?;
+        failures.remove(index).map(|failure| failure.error)
+    }
+
+    pub(crate) fn take_failures(&self) -> Vec<UpdateFailure> {
+        let mut failures = self.failures.lock().unwrap_or_else(|e| e.into_inner());
+        failures.drain(..).collect()
+    }
+
+    pub(crate) fn connection_epoch(&self) -> u32 {
+        self.connection_epoch.load(Ordering::Relaxed)
+    }
+
+    /// Wakes up waiters of the lost connection.
+    pub(crate) fn interrupt(&self) {
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        self.connection_epoch.fetch_add(1, Ordering::Relaxed);
+        drop(inner);
+        self.applied.notify_all();
+        self.notify.notify_waiters();
+    }
+
+    /// Blocks until every update below `packet_number` was applied, the connection of `epoch`
+    /// was lost or `deadline` passed. Without an epoch, keeps waiting across reconnections.
+    pub(crate) fn wait_until_applied(
+        &self,
+        packet_number: u32,
+        epoch: Option<u32>,
+        deadline: Option<Instant>,
+    ) -> Wait {
+        let interrupted = || epoch.is_some_and(|epoch| self.connection_epoch() != epoch);
+        let waiting = |_: &mut T| self.last_packet_number() < packet_number && !interrupted();
+        let inner = self.lock();
+        let _inner = match deadline {
+            Some(deadline) => {
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                self.applied
+                    .wait_timeout_while(inner, timeout, waiting)
+                    .unwrap_or_else(|e| e.into_inner())
+                    .0
+            }
+            None => self
+                .applied
+                .wait_while(inner, waiting)
+                .unwrap_or_else(|e| e.into_inner()),
+        };
+        if self.last_packet_number() >= packet_number {
+            Wait::Applied
+        } else if interrupted() {
+            Wait::Interrupted
+        } else {
+            Wait::TimedOut
+        }
+    }
+
+    /// Async counterpart of `wait_until_applied` without a deadline.
+    pub(crate) async fn applied_async(&self, packet_number: u32, epoch: u32) -> bool {
+        loop {
+            let notified = self.notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            if self.last_packet_number() >= packet_number {
+                return true;
+            }
+            if self.connection_epoch() != epoch {
+                return false;
+            }
+            notified.await;
+        }
+    }
+}
@@ -1,10 +1,16 @@
 use crate::communication::messages;
+use crate::communication::mirror::{mirror_factory, Mirror, MirrorFactory};
+use crate::communication::umessage;
+use crate::communication::wal::{GroupLog, LogConfig, Wal};
+use crate::ucore::updateable::Updatable;
 use futures::prelude::*;
-use messages::{ClientMessage, ServerMessage};
+use messages::{ClientMessage, Rejection, RejectionReason, ServerMessage};
+use serde::de::{DeserializeOwned, IgnoredAny};
 use serde_json::{json, Value};
 use std::{
     collections::HashMap,
     io::{self},
+    net::{Ipv4Addr, SocketAddr, ToSocketAddrs},
     sync::{Arc, Mutex},
 };
 use tokio::{
@@ -12,11 +18,13 @@ use tokio::{
         tcp::{OwnedReadHalf, OwnedWriteHalf},
         TcpListener, TcpStream,
     },
-    sync::broadcast::{self, Receiver, Sender},
+    sync::broadcast::{self, error::RecvError, Receiver, Sender},
+    task::{JoinHandle, JoinSet},
 };
 use tokio_serde::{formats::*, SymmetricallyFramed};
 use tokio_util::codec::{FramedRead, FramedWrite, LengthDelimitedCodec};
 use tokio_util::sync::CancellationToken;
+use umessage::{UMessage, USnapshot};
 
 use thiserror::Error;
 
@@ -30,13 +38,30 @@ pub enum ServerError {
     ReadError(String),
     #[error("Failed to acquire lock: {0}")]
     LockError(String),
+    #[error("Write-ahead log failure: {0}")]
+    StorageError(String),
+    #[error("Invalid address: {0}")]
+    AddressError(String),
+    #[error("Failed to bind listener: {0}")]
+    BindError(String),
+    #[error("Stored group state doesn't match its type: {0}")]
+    StateError(String),
+}
+
+fn to_storage_error(error: io::Error) -> ServerError {
+    ServerError::StorageError(error.to_string())
 }
 
 #[derive(Debug)]
 pub struct Group {
     broadcast_tx: broadcast::Sender<ServerMessage>,
+    /// Id of the next accepted update; accepted ids stay below `u32::MAX`.
     current_packet_number: u32,
     updates_history: Vec<ServerMessage>,
+    snapshot: Option<USnapshot>,
+    log: Option<GroupLog>,
+    /// Typed state validating the updates, for groups registered with a type.
+    mirror: Option<Mirror>,
 }
 
 impl Group {
@@ -45,6 +70,131 @@ impl Group {
             broadcast_tx,
             current_packet_number: 0,
             updates_history: vec![],
+            snapshot: None,
+            log: None,
+            mirror: None,
+        }
+    }
+
+    /// Validates further updates against a typed state rebuilt from the group's history.
+    fn validate_with(&mut self, factory: MirrorFactory) -> serde_json::Result<()> {
+        let mut mirror = Mirror::new(factory);
+        mirror.rebuild(self.snapshot.as_ref(), &self.updates_history)?;
+        self.mirror = Some(mirror);
+        Ok(())
+    }
+
+    /// Applies `umessage` to the typed state, if the group is validated.
+    fn validate(&mut self, umessage: &UMessage) -> Result<(), RejectionReason> {
+        match &mut self.mirror {
+            Some(mirror) => mirror.apply(umessage),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether `snapshot` holds the group's state at its packet id, if the group is validated.
+    /// Snapshots older than the kept one are ignored by `compact` and pass.
+    fn snapshot_matches(&self, snapshot: &USnapshot) -> bool {
+        let history_start = self.history_start();
+        match &self.mirror {
+            Some(mirror) if snapshot.packet_id >= history_start => {
+                let covered = (snapshot.packet_id - history_start) as usize;
+                mirror.matches(
+                    snapshot,
+                    self.snapshot.as_ref(),
+                    &self.updates_history[..covered],
+                )
+            }
+            _ => true,
+        }
+    }
+
+    /// Brings the typed state back to the group's head after an update failed to be stored.
+    fn revert_mirror(&mut self) {
+        if let Some(mirror) = &mut self.mirror {
+            let _ = mirror.rebuild(self.snapshot.as_ref(), &self.updates_history);
+        }
+    }
+
+    pub fn with_log(mut self, log: GroupLog) -> Self {
+        self.log = Some(log);
+        self
+    }
+
+    fn append_to_log(&mut self, umessage: &UMessage) -> io::Result<()> {
+        match &mut self.log {
+            Some(log) => log.append(umessage),
+            None => Ok(()),
+        }
+    }
+
+    fn rejection(&self, packet_id: u32, reason: RejectionReason) -> ServerMessage {
+        ServerMessage::Rejected(Rejection {
+            packet_id,
+            head: self.current_packet_number,
+            reason,
+        })
+    }
+
+    /// Packet id of the first update kept in `updates_history`.
+    fn history_start(&self) -> u32 {
+        self.snapshot
+            .as_ref()
+            .map_or(0, |snapshot| snapshot.packet_id)
+    }
+
+    /// Join response and the updates a client resuming from `resume_from` is missing.
+    /// Clients whose offset is outside the kept history receive the full state.
+    fn catch_up(
+        &self,
+        resume_from: Option<u32>,
+    ) -> (ServerMessage, Option<USnapshot>, Vec<ServerMessage>) {
+        let history_start = self.history_start();
+        match resume_from {
+            Some(packet_id)
+                if packet_id >= history_start && packet_id <= self.current_packet_number =>
+            {
+                let missing = self.updates_history[(packet_id - history_start) as usize..].to_vec();
+                (ServerMessage::Correct, None, missing)
+            }
+            Some(_) => (
+                ServerMessage::OffsetUnavailable,
+                self.snapshot.clone(),
+                self.updates_history.clone(),
+            ),
+            None => (
+                ServerMessage::Correct,
+                self.snapshot.clone(),
+                self.updates_history.clone(),
+            ),
+        }
+    }
+
+    /// Stores `snapshot` and drops the part of the history it covers.
+    /// Snapshots older than the current one are ignored.
+    fn compact(&mut self, snapshot: USnapshot) -> io::Result<()> {
+        let history_start = self.history_start();
+        if snapshot.packet_id <= history_start && self.snapshot.is_some() {
+            return Ok(());
+        }
+        if let Some(log) = &mut self.log {
+            log.write_snapshot(&snapshot)?;
+        }
+        self.updates_history
+            .drain(..(snapshot.packet_id - history_start) as usize);
+        self.snapshot = Some(snapshot);
+        Ok(())
+    }
+
+    /// Replays the snapshot and updates recovered from the write-ahead log.
+    pub fn restore(&mut self, snapshot: Option<USnapshot>, history: Vec<UMessage>) {
+        if let Some(snapshot) = &snapshot {
+            self.current_packet_number = snapshot.packet_id;
+        }
+        self.snapshot = snapshot;
+        for umessage in history {
+            self.current_packet_number = umessage.packet_id + 1;
+            self.updates_history.push(ServerMessage::Update(umessage));
         }
     }
 }
@@ -52,13 +202,64 @@ impl Group {
 #[derive(Debug)]
 pub struct ServerState {
     groups: HashMap<u32, Arc<Mutex<Group>>>,
+    wal: Option<Wal>,
+    channel_capacity: usize,
+    /// Types of the validated groups.
+    mirrors: HashMap<u32, MirrorFactory>,
 }
 
+const DEFAULT_CHANNEL_CAPACITY: usize = 16;
+
 impl ServerState {
     pub fn new() -> Self {
         Self {
             groups: HashMap::new(),
+            wal: None,
+            channel_capacity: DEFAULT_CHANNEL_CAPACITY,
+            mirrors: HashMap::new(),
+        }
+    }
+
+    /// Validates the updates of `group_id` against the state built by `factory`.
+    fn validate(&mut self, group_id: u32, factory: MirrorFactory) -> Result<(), ServerError> {
+        self.mirrors.insert(group_id, factory);
+        match self.groups.get(&group_id) {
+            Some(group) => group
+                .lock()
+                .map_err(|e| ServerError::LockError(e.to_string()))?
+                .validate_with(factory)
+                .map_err(|e| ServerError::StateError(e.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    /// Recreates the broadcast channels of the groups, which must have no subscribers yet.
+    fn set_channel_capacity(&mut self, capacity: usize) -> Result<(), ServerError> {
+        self.channel_capacity = capacity;
+        for group in self.groups.values() {
+            let mut group = group
+                .lock()
+                .map_err(|e| ServerError::LockError(e.to_string()))?;
+            group.broadcast_tx = broadcast::channel(capacity).0;
         }
+        Ok(())
+    }
+
+    /// Rebuilds every group stored in the write-ahead log.
+    pub fn recover(wal: Wal) -> io::Result<Self> {
+        let mut groups = HashMap::new();
+        for recovered in wal.recover()? {
+            let (tx, _rx) = broadcast::channel(DEFAULT_CHANNEL_CAPACITY);
+            let mut group = Group::new(tx).with_log(recovered.log);
+            group.restore(recovered.snapshot, recovered.history);
+            groups.insert(recovered.group_id, Arc::new(Mutex::new(group)));
+        }
+        Ok(Self {
+            groups,
+            wal: Some(wal),
+            channel_capacity: DEFAULT_CHANNEL_CAPACITY,
+            mirrors: HashMap::new(),
+        })
     }
 }
 
@@ -79,34 +280,164 @@ type Serializer = SymmetricallyFramed<
     SymmetricalJson<Value>,
 >;
 
+/// A running server, stopped by `shutdown` or when dropped.
+#[derive(Debug)]
+pub struct ServerHandle {
+    local_addr: SocketAddr,
+    shutdown_token: CancellationToken,
+    task: Option<JoinHandle<()>>,
+}
+
+impl ServerHandle {
+    /// The address the listener is bound to, with the actual port when port 0 was requested.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Stops accepting connections and waits until every connection task has finished.
+    pub async fn shutdown(mut self) {
+        self.shutdown_token.cancel();
+        if let Some(task) = self.task.take() {
+            let _ = task.await;
+        }
+    }
+}
+
+impl Drop for ServerHandle {
+    fn drop(&mut self) {
+        self.shutdown_token.cancel();
+    }
+}
+
 #[derive(Debug)]
 pub struct Server {
     state: Arc<Mutex<ServerState>>,
-    port: u16,
+    addresses: Vec<SocketAddr>,
 }
 
 impl Server {
+    /// Listens on `127.0.0.1:{port}`.
     pub fn new(port: u16) -> Self {
         Self {
             state: Arc::new(Mutex::new(ServerState::new())),
-            port,
+            addresses: vec![SocketAddr::from((Ipv4Addr::LOCALHOST, port))],
+        }
+    }
+
+    /// Listens on the first of the resolved addresses that can be bound,
+    /// e.g. `"0.0.0.0:7878"` or `"[::1]:7878"`.
+    pub fn with_address<A: ToSocketAddrs>(address: A) -> Result<Self, ServerError> {
+        let addresses: Vec<_> = address
+            .to_socket_addrs()
+            .map_err(|e| ServerError::AddressError(e.to_string()))?
+            .collect();
+        if addresses.is_empty() {
+            return Err(ServerError::AddressError(
+                "no address to listen on".to_owned(),
+            ));
+        }
+        Ok(Self {
+            state: Arc::new(Mutex::new(ServerState::new())),
+            addresses,
+        })
+    }
+
+    /// Persists accepted updates in a write-ahead log under `config`'s directory,
+    /// restoring the groups already stored there.
+    pub fn with_log(mut self, config: LogConfig) -> Result<Self, ServerError> {
+        let wal = Wal::open(config).map_err(to_storage_error)?;
+        let mut state = ServerState::recover(wal).map_err(to_storage_error)?;
+        let previous = self
+            .state
+            .lock()
+            .map_err(|e| ServerError::LockError(e.to_string()))?;
+        state.set_channel_capacity(previous.channel_capacity)?;
+        for (&group_id, &factory) in &previous.mirrors {
+            state.validate(group_id, factory)?;
         }
+        drop(previous);
+        self.state = Arc::new(Mutex::new(state));
+        Ok(self)
     }
 
-    pub async fn run(&self, shutdown_token: CancellationToken) {
-        let listener = TcpListener::bind(format!("127.0.0.1:{}", self.port))
+    /// Keeps a typed copy of group `group_id`'s state and rejects updates that don't fit it
+    /// with `RejectionReason::InvalidUpdate`, instead of forwarding them to every client.
+    pub fn with_validated_group<T>(self, group_id: u32) -> Result<Self, ServerError>
+    where
+        T: Updatable + Clone + PartialEq + Default + DeserializeOwned + Send + 'static,
+        T::Update: DeserializeOwned,
+    {
+        self.state
+            .lock()
+            .map_err(|e| ServerError::LockError(e.to_string()))?
+            .validate(group_id, mirror_factory::<T>())?;
+        Ok(self)
+    }
+
+    /// Number of updates buffered per group for connections that are slow to forward them.
+    /// Connections falling further behind catch up from the group's history. At least 1.
+    pub fn with_channel_capacity(self, capacity: usize) -> Result<Self, ServerError> {
+        self.state
+            .lock()
+            .map_err(|e| ServerError::LockError(e.to_string()))?
+            .set_channel_capacity(capacity.max(1))?;
+        Ok(self)
+    }
+
+    /// Binds `address` (port 0 picks a free one) and serves connections in the background.
+    pub async fn bind<A: ToSocketAddrs>(address: A) -> Result<ServerHandle, ServerError> {
+        Self::with_address(address)?.start().await
+    }
+
+    /// Binds the listener and serves connections in the background.
+    /// Returns once the listener is ready to accept connections.
+    pub async fn start(self) -> Result<ServerHandle, ServerError> {
+        let listener = self.listen().await?;
+        let local_addr = listener
+            .local_addr()
+            .map_err(|e| ServerError::BindError(e.to_string()))?;
+        let shutdown_token = CancellationToken::new();
+        let task = tokio::spawn(Self::serve(listener, self.state, shutdown_token.clone()));
+        Ok(ServerHandle {
+            local_addr,
+            shutdown_token,
+            task: Some(task),
+        })
+    }
+
+    /// Serves connections until `shutdown_token` is cancelled and every connection is closed.
+    pub async fn run(&self, shutdown_token: CancellationToken) -> Result<(), ServerError> {
+        let listener = self.listen().await?;
+        Self::serve(listener, self.state.clone(), shutdown_token).await;
+        Ok(())
+    }
+
+    async fn listen(&self) -> Result<TcpListener, ServerError> {
+        TcpListener::bind(&self.addresses[..])
             .await
-            .unwrap();
+            .map_err(|e| ServerError::BindError(e.to_string()))
+    }
 
+    async fn serve(
+        listener: TcpListener,
+        state: Arc<Mutex<ServerState>>,
+        shutdown_token: CancellationToken,
+    ) {
+        let connections_token = shutdown_token.child_token();
+        let mut connections = JoinSet::new();
         loop {
             tokio::select! {
                 result = listener.accept() => {
                     match result {
                         Ok((socket, _)) => {
-                            let state = self.state.clone();
-                            let token = shutdown_token.clone();
-                            tokio::spawn(async move {
-                                if let Err(e) = Server::handle_connection(socket, state, token).await {
+                            let state = state.clone();
+                            let token = connections_token.clone();
+                            connections.spawn(async move {
+                                let result = tokio::select! {
+                                    result = Server::handle_connection(socket, state, token.clone()) => result,
+                                    _ = token.cancelled() => Ok(()),
+                                };
+                                if let Err(e) = result {
                                     eprintln!("Connection handling failed: {}", e);
                                 }
                             });
@@ -117,11 +448,15 @@ impl Server {
                         }
                     }
                 }
+                Some(_) = connections.join_next(), if !connections.is_empty() => {}
                 _ = shutdown_token.cancelled() => {
                     break;
                 }
             }
         }
+        drop(listener);
+        connections_token.cancel();
+        while connections.join_next().await.is_some() {}
     }
 
     fn create_deserializer(reader: OwnedReadHalf) -> Deserializer {
@@ -144,42 +479,46 @@ impl Server {
         let mut deserialized = Self::create_deserializer(reader);
         let mut serialized = Self::create_serializer(writer);
 
-        let group_id = Self::read_group_id(&mut deserialized).await?;
+        let (group_id, resume_from) = Self::read_join_request(&mut deserialized).await?;
 
-        serialized
-            .send(json!(ServerMessage::Correct))
-            .await
-            .map_err(|_e| ServerError::SendError("Initial message".into()))?;
+        let group = Self::get_or_create_group(group_id, &state)?;
 
-        let group = Self::get_or_create_group(group_id, &state);
-
-        let (tx, rx, history) = {
+        let (tx, rx, join_response, snapshot, history, next_packet_id) = {
             let group_lock = group
                 .lock()
                 .map_err(|e| ServerError::LockError(e.to_string()))?;
 
             let tx = group_lock.broadcast_tx.clone();
-            let history = group_lock.updates_history.clone();
+            let (join_response, snapshot, history) = group_lock.catch_up(resume_from);
             let rx = tx.subscribe();
-            (tx, rx, history)
+            let next_packet_id = group_lock.current_packet_number;
+            (tx, rx, join_response, snapshot, history, next_packet_id)
         };
 
-        Self::send_group_history(history, &mut serialized).await?;
+        serialized
+            .send(json!(join_response))
+            .await
+            .map_err(|_e| ServerError::SendError("Initial message".into()))?;
+
+        Self::send_group_history(snapshot, history, &mut serialized).await?;
         Self::process_messages(
             &mut deserialized,
             &mut serialized,
             group,
             tx,
             rx,
+            next_packet_id,
             shutdown_token,
         )
         .await
     }
 
-    async fn read_group_id(deserialized: &mut Deserializer) -> Result<u32, ServerError> {
+    async fn read_join_request(
+        deserialized: &mut Deserializer,
+    ) -> Result<(u32, Option<u32>), ServerError> {
         match deserialized.try_next().await {
             Ok(Some(value)) => match serde_json::from_value::<ClientMessage>(value) {
-                Ok(ClientMessage::JoinGroup(group_id)) => Ok(group_id),
+                Ok(ClientMessage::JoinGroup(group_id, resume_from)) => Ok((group_id, resume_from)),
                 Ok(_) => Err(ServerError::CommunicationError(
                     "Unexpected message while reading Group ID".into(),
                 )),
@@ -194,22 +533,41 @@ impl Server {
         }
     }
 
-    fn get_or_create_group(group_id: u32, state: &Arc<Mutex<ServerState>>) -> Arc<Mutex<Group>> {
-        let mut state_lock = state.lock().unwrap();
-        state_lock
-            .groups
-            .entry(group_id)
-            .or_insert_with(|| {
-                let (tx, _rx) = broadcast::channel(16);
-                Arc::new(Mutex::new(Group::new(tx)))
-            })
-            .clone()
+    fn get_or_create_group(
+        group_id: u32,
+        state: &Arc<Mutex<ServerState>>,
+    ) -> Result<Arc<Mutex<Group>>, ServerError> {
+        let mut state_lock = state
+            .lock()
+            .map_err(|e| ServerError::LockError(e.to_string()))?;
+        if let Some(group) = state_lock.groups.get(&group_id) {
+            return Ok(group.clone());
+        }
+
+        let (tx, _rx) = broadcast::channel(state_lock.channel_capacity);
+        let mut group = Group::new(tx);
+        if let Some(wal) = &state_lock.wal {
+            group = group.with_log(wal.create_group(group_id).map_err(to_storage_error)?);
+        }
+        if let Some(&factory) = state_lock.mirrors.get(&group_id) {
+            group.mirror = Some(Mirror::new(factory));
+        }
+        let group = Arc::new(Mutex::new(group));
+        state_lock.groups.insert(group_id, group.clone());
+        Ok(group)
     }
 
     async fn send_group_history(
+        snapshot: Option<USnapshot>,
         history: Vec<ServerMessage>,
         serialized: &mut Serializer,
     ) -> Result<(), ServerError> {
+        if let Some(snapshot) = snapshot {
+            serialized
+                .send(json!(ServerMessage::Snapshot(snapshot)))
+                .await
+                .map_err(|_e| ServerError::SendError("History snapshot".into()))?;
+        }
         for update in history {
             dbg!("Server sending | {}", &update);
 
@@ -221,12 +579,42 @@ impl Server {
         Ok(())
     }
 
+    /// Sends a lagging client the updates from `next_packet_id` on, or the full state
+    /// if they were compacted. Returns the packet id the client expects next.
+    async fn resend_missed(
+        group: &Arc<Mutex<Group>>,
+        next_packet_id: u32,
+        serialized: &mut Serializer,
+    ) -> Result<u32, ServerError> {
+        let (response, snapshot, history, current_packet_number) = {
+            let group_lock = group
+                .lock()
+                .map_err(|e| ServerError::LockError(e.to_string()))?;
+            let (response, snapshot, history) = group_lock.catch_up(Some(next_packet_id));
+            (
+                response,
+                snapshot,
+                history,
+                group_lock.current_packet_number,
+            )
+        };
+        if let ServerMessage::OffsetUnavailable = response {
+            serialized
+                .send(json!(response))
+                .await
+                .map_err(|_e| ServerError::SendError("Resync notice".into()))?;
+        }
+        Self::send_group_history(snapshot, history, serialized).await?;
+        Ok(current_packet_number)
+    }
+
     async fn process_messages(
         deserialized: &mut Deserializer,
         serialized: &mut Serializer,
         group: Arc<Mutex<Group>>,
         tx: Sender<ServerMessage>,
         mut rx: Receiver<ServerMessage>,
+        mut next_packet_id: u32,
         shutdown_token: CancellationToken,
     ) -> Result<(), ServerError> {
         loop {
@@ -235,11 +623,30 @@ impl Server {
                     Self::handle_incoming_message(msg, &group, &tx, serialized).await?;
                 }
                 message = rx.recv() => {
-                    if let Ok(update) = message {
-                        serialized
-                        .send(json!(update))
-                        .await
-                        .map_err(|_e| ServerError::SendError("Broadcast message".into()))?;
+                    match message {
+                        Ok(ServerMessage::Update(umessage)) => {
+                            // Already sent while catching up after a lag.
+                            if umessage.packet_id < next_packet_id {
+                                continue;
+                            }
+                            next_packet_id = umessage.packet_id + 1;
+                            serialized
+                                .send(json!(ServerMessage::Update(umessage)))
+                                .await
+                                .map_err(|_e| ServerError::SendError("Broadcast message".into()))?;
+                        }
+                        Ok(message) => {
+                            serialized
+                                .send(json!(message))
+                                .await
+                                .map_err(|_e| ServerError::SendError("Broadcast message".into()))?;
+                        }
+                        Err(RecvError::Lagged(skipped)) => {
+                            eprintln!("Client lagged behind by {} updates, catching up", skipped);
+                            next_packet_id =
+                                Self::resend_missed(&group, next_packet_id, serialized).await?;
+                        }
+                        Err(RecvError::Closed) => return Ok(()),
                     }
                 }
                 _ = shutdown_token.cancelled() => {
@@ -269,39 +676,29 @@ impl Server {
             }
         };
 
-        let umessage = match serde_json::from_value(msg) {
+        let server_response = match serde_json::from_value(msg) {
+            Ok(ClientMessage::Update(umessage))
+                if serde_json::from_str::<IgnoredAny>(&umessage.update).is_err() =>
+            {
+                Self::reject(umessage.packet_id, group, RejectionReason::MalformedPayload)?
+            }
             Ok(ClientMessage::Update(umessage)) => {
                 dbg!("Server received UMessage | {}", &umessage);
-                umessage
+                Self::accept_update(umessage, group, tx)?
+            }
+            Ok(ClientMessage::Snapshot(snapshot)) => Self::accept_snapshot(snapshot, group)?,
+            Ok(ClientMessage::Head) => {
+                let group_lock = group
+                    .lock()
+                    .map_err(|e| ServerError::LockError(e.to_string()))?;
+                ServerMessage::Head(group_lock.current_packet_number)
             }
             Ok(_) => {
                 return Err(ServerError::CommunicationError(
                     "Unexpected message from client".into(),
                 ));
             }
-            Err(_e) => {
-                return Err(ServerError::ReadError(
-                    "Failed to deserialize message".into(),
-                ));
-            }
-        };
-
-        let server_response = {
-            let mut group_lock = group
-                .lock()
-                .map_err(|e| ServerError::LockError(e.to_string()))?;
-
-            if umessage.packet_id != group_lock.current_packet_number {
-                ServerMessage::Error
-            } else {
-                group_lock.current_packet_number += 1;
-                let umessage = ServerMessage::Update(umessage);
-                group_lock.updates_history.push(umessage.clone());
-                tx.send(umessage)
-                    .map_err(|_e| ServerError::SendError("Failed to broadcast message".into()))?;
-
-                ServerMessage::Correct
-            }
+            Err(_e) => Self::reject(0, group, RejectionReason::MalformedPayload)?,
         };
 
         dbg!("Server sending | {}", &server_response);
@@ -312,4 +709,66 @@ impl Server {
 
         Ok(())
     }
+
+    fn accept_update(
+        umessage: UMessage,
+        group: &Arc<Mutex<Group>>,
+        tx: &broadcast::Sender<ServerMessage>,
+    ) -> Result<ServerMessage, ServerError> {
+        let mut group_lock = group
+            .lock()
+            .map_err(|e| ServerError::LockError(e.to_string()))?;
+
+        let packet_id = umessage.packet_id;
+        if packet_id != group_lock.current_packet_number {
+            Ok(group_lock.rejection(packet_id, RejectionReason::StalePacketId))
+        } else if packet_id.checked_add(1).is_none() {
+            // The head can't move past the last id.
+            Ok(group_lock.rejection(packet_id, RejectionReason::PacketIdsExhausted))
+        } else if let Err(reason) = group_lock.validate(&umessage) {
+            Ok(group_lock.rejection(packet_id, reason))
+        } else if let Err(e) = group_lock.append_to_log(&umessage) {
+            eprintln!("Failed to append update to the log: {}", e);
+            group_lock.revert_mirror();
+            Ok(group_lock.rejection(packet_id, RejectionReason::StorageFailure))
+        } else {
+            group_lock.current_packet_number = packet_id + 1;
+            let umessage = ServerMessage::Update(umessage);
+            group_lock.updates_history.push(umessage.clone());
+            tx.send(umessage)
+                .map_err(|_e| ServerError::SendError("Failed to broadcast message".into()))?;
+
+            Ok(ServerMessage::Accepted(packet_id))
+        }
+    }
+
+    fn accept_snapshot(
+        snapshot: USnapshot,
+        group: &Arc<Mutex<Group>>,
+    ) -> Result<ServerMessage, ServerError> {
+        let mut group_lock = group
+            .lock()
+            .map_err(|e| ServerError::LockError(e.to_string()))?;
+
+        let packet_id = snapshot.packet_id;
+        if packet_id > group_lock.current_packet_number || !group_lock.snapshot_matches(&snapshot) {
+            Ok(group_lock.rejection(packet_id, RejectionReason::InvalidSnapshot))
+        } else if let Err(e) = group_lock.compact(snapshot) {
+            eprintln!("Failed to store snapshot: {}", e);
+            Ok(group_lock.rejection(packet_id, RejectionReason::StorageFailure))
+        } else {
+            Ok(ServerMessage::Accepted(packet_id))
+        }
+    }
+
+    fn reject(
+        packet_id: u32,
+        group: &Arc<Mutex<Group>>,
+        reason: RejectionReason,
+    ) -> Result<ServerMessage, ServerError> {
+        let group_lock = group
+            .lock()
+            .map_err(|e| ServerError::LockError(e.to_string()))?;
+        Ok(group_lock.rejection(packet_id, reason))
+    }
 }
@@ -0,0 +1,495 @@
+use crate::ucore::ubtreemap::{UBTreeMap, UBTreeMapUpdate};
+use crate::ucore::udeque::{UDeque, UDequeUpdate};
+use crate::ucore::umap::{UMap, UMapUpdate};
+use crate::ucore::updateable::Updatable;
+use crate::ucore::uset::{USet, USetUpdate};
+use crate::ucore::ustack::{UStack, UStackUpdate};
+use crate::ucore::uvec::{UVec, UVecUpdate};
+use futures::channel::mpsc as stream;
+use serde::Serialize;
+use std::hash::Hash;
+use std::ops::RangeBounds;
+use std::sync::{mpsc, Mutex};
+
+/// A change applied to the local state of a shared structure.
+#[derive(Debug)]
+pub enum Change<U> {
+    /// `update` was applied as packet `packet_id`.
+    Update { packet_id: u32, update: U },
+    /// The state was replaced wholesale: by a snapshot covering every packet below
+    /// `packet_id`, or by the default state when `packet_id` is 0.
+    Replaced { packet_id: u32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubscriptionId(u64);
+
+/// Decides from an update and the state it applies to whether a subscriber is notified.
+type Filter<T> = Box<dyn Fn(&<T as Updatable>::Update, &T) -> bool + Send>;
+
+enum Sink<U> {
+    Callback(Box<dyn FnMut(Change<U>) + Send>),
+    Channel(mpsc::Sender<Change<U>>),
+    Stream(stream::UnboundedSender<Change<U>>),
+}
+
+impl<U> Sink<U> {
+    /// Returns `false` once the receiving end is gone.
+    fn deliver(&mut self, change: Change<U>) -> bool {
+        match self {
+            Sink::Callback(callback) => {
+                callback(change);
+                true
+            }
+            Sink::Channel(sender) => sender.send(change).is_ok(),
+            Sink::Stream(sender) => sender.unbounded_send(change).is_ok(),
+        }
+    }
+}
+
+struct Subscriber<T: Updatable> {
+    id: SubscriptionId,
+    filter: Option<Filter<T>>,
+    sink: Sink<T::Update>,
+}
+
+/// Subscribers to the changes applied to a replica.
+pub(crate) struct Subscriptions<T: Updatable> {
+    next_id: u64,
+    subscribers: Vec<Subscriber<T>>,
+}
+
+impl<T: Updatable> Subscriptions<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            next_id: 0,
+            subscribers: vec![],
+        }
+    }
+
+    fn add(&mut self, filter: Option<Filter<T>>, sink: Sink<T::Update>) -> SubscriptionId {
+        let id = SubscriptionId(self.next_id);
+        self.next_id += 1;
+        self.subscribers.push(Subscriber { id, filter, sink });
+        id
+    }
+
+    pub(crate) fn remove(&mut self, id: SubscriptionId) {
+        self.subscribers.retain(|subscriber| subscriber.id != id);
+    }
+
+    /// Subscribers interested in `update`, evaluated against the state before it's applied.
+    pub(crate) fn interested(&self, update: &T::Update, state: &T) -> Vec<SubscriptionId> {
+        self.subscribers
+            .iter()
+            .filter(|subscriber| {
+                subscriber
+                    .filter
+                    .as_ref()
+                    .is_none_or(|filter| filter(update, state))
+            })
+            .map(|subscriber| subscriber.id)
+            .collect()
+    }
+
+    /// Delivers a change built by `change` to each of `ids`, dropping subscribers that went away.
+    pub(crate) fn notify<E>(
+        &mut self,
+        ids: &[SubscriptionId],
+        mut change: impl FnMut() -> Result<Change<T::Update>, E>,
+    ) -> Result<(), E> {
+        let mut gone = vec![];
+        for subscriber in self.subscribers.iter_mut() {
+            if ids.contains(&subscriber.id) && !subscriber.sink.deliver(change()?) {
+                gone.push(subscriber.id);
+            }
+        }
+        self.subscribers
+            .retain(|subscriber| !gone.contains(&subscriber.id));
+        Ok(())
+    }
+
+    /// Notifies every subscriber that the state was replaced.
+    pub(crate) fn replaced(&mut self, packet_id: u32) {
+        let ids: Vec<_> = self
+            .subscribers
+            .iter()
+            .map(|subscriber| subscriber.id)
+            .collect();
+        let _ = self.notify::<()>(&ids, || Ok(Change::Replaced { packet_id }));
+    }
+}
+
+/// Builder of a subscription to the changes applied to a shared structure.
+/// Callbacks run on the connection's reader; they may read the structure but must not
+/// publish to it, subscribe or unsubscribe.
+pub struct Subscribe<'a, T: Updatable> {
+    subscriptions: &'a Mutex<Subscriptions<T>>,
+    filter: Option<Filter<T>>,
+}
+
+impl<'a, T> Subscribe<'a, T>
+where
+    T: Updatable,
+    T::Update: Send + 'static,
+{
+    pub(crate) fn new(subscriptions: &'a Mutex<Subscriptions<T>>) -> Self {
+        Self {
+            subscriptions,
+            filter: None,
+        }
+    }
+
+    fn add(self, sink: Sink<T::Update>) -> SubscriptionId {
+        self.subscriptions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .add(self.filter, sink)
+    }
+
+    /// Calls `callback` with every change; stops once unsubscribed.
+    pub fn callback<F: FnMut(Change<T::Update>) + Send + 'static>(
+        self,
+        callback: F,
+    ) -> SubscriptionId {
+        self.add(Sink::Callback(Box::new(callback)))
+    }
+
+    /// Sends every change to the returned receiver; stops once it's dropped.
+    pub fn channel(self) -> mpsc::Receiver<Change<T::Update>> {
+        let (sender, receiver) = mpsc::channel();
+        self.add(Sink::Channel(sender));
+        receiver
+    }
+
+    /// Yields every change from the returned stream; stops once it's dropped.
+    pub fn stream(self) -> stream::UnboundedReceiver<Change<T::Update>> {
+        let (sender, receiver) = stream::unbounded();
+        self.add(Sink::Stream(sender));
+        receiver
+    }
+}
+
+impl<K, T> Subscribe<'_, UMap<K, T>>
+where
+    K: Eq + Hash + Clone + Serialize + Send + 'static,
+    T: Updatable + Clone + Serialize,
+    <T as Updatable>::Update: Serialize,
+{
+    /// Only delivers changes to the entry at `key`.
+    pub fn key(mut self, key: K) -> Self {
+        self.filter = Some(Box::new(move |update, state| {
+            entry_touched(&key, update, state, &|_, _| true)
+        }));
+        self
+    }
+}
+
+impl<K, T> Subscribe<'_, UBTreeMap<K, T>>
+where
+    K: Ord + Clone + Serialize + Send + 'static,
+    T: Updatable + Clone + Serialize,
+    <T as Updatable>::Update: Serialize,
+{
+    /// Only delivers changes to the entry at `key`.
+    pub fn key(mut self, key: K) -> Self {
+        self.filter = Some(Box::new(move |update, state| {
+            ordered_entry_touched(&key, update, state, &|_, _| true)
+        }));
+        self
+    }
+}
+
+impl<T> Subscribe<'_, UVec<T>>
+where
+    T: Updatable + Clone + Serialize,
+    <T as Updatable>::Update: Serialize,
+{
+    /// Only delivers changes to the element at `index`, including shifts into or out of it.
+    pub fn index(mut self, index: usize) -> Self {
+        self.filter = Some(Box::new(move |update, state| {
+            element_touched(index, update, state, &|_, _| true)
+        }));
+        self
+    }
+}
+
+/// Decides whether a nested update touches the watched subtree of the state it applies to.
+type Nested<'n, T> = &'n dyn Fn(&<T as Updatable>::Update, &T) -> bool;
+
+/// Decides whether an update of the root touches the watched subtree, given how
+/// nested updates of the current node are decided.
+type Probe<R, C> =
+    Box<dyn Fn(&<R as Updatable>::Update, &R, Nested<'_, C>) -> bool + Send + 'static>;
+
+/// Whether `update` touches the entry at `key`.
+fn entry_touched<K, T>(
+    key: &K,
+    update: &UMapUpdate<K, T>,
+    state: &UMap<K, T>,
+    nested: Nested<'_, T>,
+) -> bool
+where
+    K: Eq + Hash + Clone + Serialize,
+    T: Updatable + Clone + Serialize,
+    <T as Updatable>::Update: Serialize,
+{
+    match update {
+        UMapUpdate::Insert(k, _) | UMapUpdate::Remove(k) => k == key,
+        UMapUpdate::Nested(k, update) => {
+            k == key && state.get_ref(k).is_none_or(|entry| nested(update, entry))
+        }
+    }
+}
+
+/// Whether `update` touches the entry at `key` of an ordered map.
+fn ordered_entry_touched<K, T>(
+    key: &K,
+    update: &UBTreeMapUpdate<K, T>,
+    state: &UBTreeMap<K, T>,
+    nested: Nested<'_, T>,
+) -> bool
+where
+    K: Ord + Clone + Serialize,
+    T: Updatable + Clone + Serialize,
+    <T as Updatable>::Update: Serialize,
+{
+    match update {
+        UBTreeMapUpdate::Insert(k, _) | UBTreeMapUpdate::Remove(k) => k == key,
+        UBTreeMapUpdate::RemoveRange(start, end) => (start.as_ref(), end.as_ref()).contains(key),
+        UBTreeMapUpdate::Nested(k, update) => {
+            k == key && state.get_ref(k).is_none_or(|entry| nested(update, entry))
+        }
+    }
+}
+
+/// Whether `update` touches the element at `index`, including shifts into or out of it.
+fn element_touched<T>(
+    index: usize,
+    update: &UVecUpdate<T>,
+    state: &UVec<T>,
+    nested: Nested<'_, T>,
+) -> bool
+where
+    T: Updatable + Clone + Serialize,
+    <T as Updatable>::Update: Serialize,
+{
+    let len = state.len();
+    match update {
+        UVecUpdate::Clear => index < len,
+        UVecUpdate::Insert(i, _) => *i <= index && index <= len,
+        UVecUpdate::Remove(i) => *i <= index && index < len,
+        UVecUpdate::Push(_) => index == len,
+        UVecUpdate::Pop => index + 1 == len,
+        UVecUpdate::Nested(i, update) => {
+            *i == index
+                && state
+                    .get_ref(index)
+                    .is_none_or(|element| nested(update, element))
+        }
+    }
+}
+
+/// Whether `update` touches the top of the stack.
+fn top_touched<T>(update: &UStackUpdate<T>, state: &UStack<T>, nested: Nested<'_, T>) -> bool
+where
+    T: Updatable + Clone + Serialize,
+    <T as Updatable>::Update: Serialize,
+{
+    match update {
+        UStackUpdate::Push(_) | UStackUpdate::Pop => true,
+        UStackUpdate::Nested(update) => state.top_ref().is_none_or(|top| nested(update, top)),
+    }
+}
+
+/// Whether `update` touches the front of the deque, or its back if `back`.
+fn end_touched<T>(
+    back: bool,
+    update: &UDequeUpdate<T>,
+    state: &UDeque<T>,
+    nested: Nested<'_, T>,
+) -> bool
+where
+    T: Updatable + Clone + Serialize,
+    <T as Updatable>::Update: Serialize,
+{
+    let end = if back {
+        state.back_ref()
+    } else {
+        state.front_ref()
+    };
+    let same_end = |at_back: bool| at_back == back || state.len() <= 1;
+    match update {
+        UDequeUpdate::PushFront(_) => !back || state.is_empty(),
+        UDequeUpdate::PushBack(_) => back || state.is_empty(),
+        UDequeUpdate::PopFront => same_end(false),
+        UDequeUpdate::PopBack => same_end(true),
+        UDequeUpdate::NestedFront(update) => {
+            same_end(false) && end.is_none_or(|element| nested(update, element))
+        }
+        UDequeUpdate::NestedBack(update) => {
+            same_end(true) && end.is_none_or(|element| nested(update, element))
+        }
+    }
+}
+
+/// Whether `update` changes whether `key` is a member of the set.
+fn member_touched<K>(key: &K, update: &USetUpdate<K>, state: &USet<K>) -> bool
+where
+    K: Eq + Hash + Clone + Serialize,
+{
+    let member = state.contains(key);
+    match update {
+        USetUpdate::Insert(k) => k == key && !member,
+        USetUpdate::Remove(k) => k == key && member,
+        USetUpdate::Clear => member,
+        USetUpdate::Extend(keys) => !member && keys.contains(key),
+    }
+}
+
+/// Builder of a subscription to the changes touching a subtree of a shared structure,
+/// navigated like the `get_mut` chains, e.g. `map.watch().key(k).index(3)`.
+pub struct Watch<'a, R: Updatable, C: Updatable> {
+    subscriptions: &'a Mutex<Subscriptions<R>>,
+    probe: Probe<R, C>,
+}
+
+impl<'a, R> Watch<'a, R, R>
+where
+    R: Updatable + 'static,
+{
+    pub(crate) fn new(subscriptions: &'a Mutex<Subscriptions<R>>) -> Self {
+        Self {
+            subscriptions,
+            probe: Box::new(|update, state, nested| nested(update, state)),
+        }
+    }
+}
+
+impl<'a, R, C> Watch<'a, R, C>
+where
+    R: Updatable + 'static,
+    R::Update: Send + 'static,
+    C: Updatable + 'static,
+{
+    fn descend<N: Updatable + 'static>(
+        self,
+        probe: impl Fn(&C::Update, &C, Nested<'_, N>) -> bool + Send + 'static,
+    ) -> Watch<'a, R, N> {
+        let outer = self.probe;
+        Watch {
+            subscriptions: self.subscriptions,
+            probe: Box::new(move |update, state, nested| {
+                outer(update, state, &|update, state| probe(update, state, nested))
+            }),
+        }
+    }
+
+    fn subscribe(self) -> Subscribe<'a, R> {
+        let probe = self.probe;
+        Subscribe {
+            subscriptions: self.subscriptions,
+            filter: Some(Box::new(move |update, state| {
+                probe(update, state, &|_, _| true)
+            })),
+        }
+    }
+
+    /// Calls `callback` with every change touching the subtree; stops once unsubscribed.
+    pub fn callback<F: FnMut(Change<R::Update>) + Send + 'static>(
+        self,
+        callback: F,
+    ) -> SubscriptionId {
+        self.subscribe().callback(callback)
+    }
+
+    /// Sends every change touching the subtree to the returned receiver.
+    pub fn channel(self) -> mpsc::Receiver<Change<R::Update>> {
+        self.subscribe().channel()
+    }
+
+    /// Yields every change touching the subtree from the returned stream.
+    pub fn stream(self) -> stream::UnboundedReceiver<Change<R::Update>> {
+        self.subscribe().stream()
+    }
+}
+
+impl<'a, R, K, T> Watch<'a, R, UMap<K, T>>
+where
+    R: Updatable + 'static,
+    R::Update: Send + 'static,
+    K: Eq + Hash + Clone + Serialize + Send + 'static,
+    T: Updatable + Clone + Serialize + 'static,
+    <T as Updatable>::Update: Serialize,
+{
+    pub fn key(self, key: K) -> Watch<'a, R, T> {
+        self.descend(move |update, state, nested| entry_touched(&key, update, state, nested))
+    }
+}
+
+impl<'a, R, K, T> Watch<'a, R, UBTreeMap<K, T>>
+where
+    R: Updatable + 'static,
+    R::Update: Send + 'static,
+    K: Ord + Clone + Serialize + Send + 'static,
+    T: Updatable + Clone + Serialize + 'static,
+    <T as Updatable>::Update: Serialize,
+{
+    pub fn key(self, key: K) -> Watch<'a, R, T> {
+        self.descend(move |update, state, nested| {
+            ordered_entry_touched(&key, update, state, nested)
+        })
+    }
+}
+
+impl<'a, R, T> Watch<'a, R, UVec<T>>
+where
+    R: Updatable + 'static,
+    R::Update: Send + 'static,
+    T: Updatable + Clone + Serialize + 'static,
+    <T as Updatable>::Update: Serialize,
+{
+    pub fn index(self, index: usize) -> Watch<'a, R, T> {
+        self.descend(move |update, state, nested| element_touched(index, update, state, nested))
+    }
+}
+
+impl<'a, R, T> Watch<'a, R, UStack<T>>
+where
+    R: Updatable + 'static,
+    R::Update: Send + 'static,
+    T: Updatable + Clone + Serialize + 'static,
+    <T as Updatable>::Update: Serialize,
+{
+    pub fn top(self) -> Watch<'a, R, T> {
+        self.descend(top_touched)
+    }
+}
+
+impl<'a, R, T> Watch<'a, R, UDeque<T>>
+where
+    R: Updatable + 'static,
+    R::Update: Send + 'static,
+    T: Updatable + Clone + Serialize + 'static,
+    <T as Updatable>::Update: Serialize,
+{
+    pub fn front(self) -> Watch<'a, R, T> {
+        self.descend(|update, state, nested| end_touched(false, update, state, nested))
+    }
+
+    pub fn back(self) -> Watch<'a, R, T> {
+        self.descend(|update, state, nested| end_touched(true, update, state, nested))
+    }
+}
+
+impl<'a, R, K> Watch<'a, R, USet<K>>
+where
+    R: Updatable + 'static,
+    R::Update: Send + 'static,
+    K: Eq + Hash + Clone + Serialize + Send + 'static,
+{
+    /// Watches whether `key` is a member, ignoring updates that don't change it.
+    pub fn contains(self, key: K) -> Watch<'a, R, bool> {
+        self.descend(move |update, state, _| member_touched(&key, update, state))
+    }
+}
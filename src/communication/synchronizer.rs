@@ -1,63 +1,462 @@
-use crate::communication::messages::{ClientMessage, ServerMessage};
-use crate::communication::umessage::UMessage;
+use crate::communication::messages::{ClientMessage, Rejection, RejectionReason, ServerMessage};
+use crate::communication::replica::{Replica, Wait};
+use crate::communication::subscription::{Change, Subscribe, SubscriptionId, Watch};
+use crate::communication::umessage::{UMessage, USnapshot};
 use crate::ucore::updateable;
 use serde::{Deserialize, Serialize};
 use serde_json::to_vec;
+use std::collections::VecDeque;
 use std::io::{Read, Write};
-use std::net::TcpStream;
-use std::sync::atomic::AtomicU32;
+use std::net::{Ipv4Addr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::channel;
-use std::sync::mpsc::Sender;
+use std::sync::mpsc::{RecvTimeoutError, Sender};
 use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
 use std::{result, thread};
+use thiserror::Error;
 use tokio_util::bytes::BytesMut;
 use tokio_util::codec::{Decoder, Encoder, LengthDelimitedCodec};
-use updateable::Updatable;
+use updateable::{Updatable, UpdateError};
 
 pub struct Synchronizer<T>
 where
     T: Updatable + Serialize,
     <T as Updatable>::Update: Serialize,
 {
-    inner: Arc<Mutex<T>>,
-    last_packet_number: Arc<AtomicU32>,
+    shared: Arc<Shared<T>>,
+}
+
+/// State shared between a `Synchronizer` and its background threads.
+/// A lock poisoned by a panic fails the requests that need it with `SError::LockError`,
+/// while the accessors fall back to defaults and the status reads `Failed`.
+struct Shared<T: Updatable> {
+    replica: Replica<T>,
     group_id: u32,
-    connection: TcpStream,
+    connection: Mutex<Option<TcpStream>>,
+    /// Holding the lock serializes requests.
+    responses: Mutex<Responses>,
+    status: Mutex<ConnectionStatus>,
+    offline: Mutex<OfflineQueue<T::Update>>,
+    retry_policy: Mutex<RetryPolicy>,
+    /// Default time limit of a request.
+    timeout: Mutex<Option<Duration>>,
+    /// Error that made the server's messages unusable, ending the connection for good.
+    failure: Mutex<Option<SError>>,
+    closed: AtomicBool,
+}
+
+/// Responses to the requests sent, in order.
+struct Responses {
     receiver: mpsc::Receiver<ResponseType>,
+    /// Requests sent on the current connection and not answered yet,
+    /// including ones that timed out.
+    outstanding: u32,
+}
+
+impl Responses {
+    /// Drops the responses to requests that timed out or whose connection was lost.
+    fn discard_stale(&mut self) {
+        while let Ok(response) = self.receiver.try_recv() {
+            self.answered(&response);
+        }
+    }
+
+    fn answered(&mut self, response: &ResponseType) {
+        match response {
+            ResponseType::Disconnected => self.outstanding = 0,
+            _ => self.outstanding = self.outstanding.saturating_sub(1),
+        }
+    }
+
+    /// Receives the response to the latest request, skipping the ones to earlier requests.
+    fn receive(&mut self, deadline: Option<Instant>) -> Result<ResponseType> {
+        loop {
+            let response = match deadline {
+                Some(deadline) => self
+                    .receiver
+                    .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                    .map_err(|error| match error {
+                        RecvTimeoutError::Timeout => deadline_passed(),
+                        RecvTimeoutError::Disconnected => to_internal_error(error),
+                    })?,
+                None => self.receiver.recv().map_err(to_internal_error)?,
+            };
+            self.answered(&response);
+            if self.outstanding == 0 {
+                return Ok(response);
+            }
+        }
+    }
+}
+
+/// Writes accepted while the server is unreachable, replayed in order after reconnecting.
+struct OfflineQueue<U> {
+    capacity: Option<usize>,
+    pending: VecDeque<(u64, Vec<U>)>,
+    /// Whether a write taken from `pending` is being replayed.
+    replaying: bool,
+    next_id: u64,
+    reports: Vec<WriteReport>,
+}
+
+impl<U> OfflineQueue<U> {
+    fn new() -> Self {
+        Self {
+            capacity: None,
+            pending: VecDeque::new(),
+            replaying: false,
+            next_id: 0,
+            reports: vec![],
+        }
+    }
+
+    fn enqueue(&mut self, updates: Vec<U>) -> Result<u64> {
+        if self
+            .capacity
+            .is_some_and(|capacity| self.pending.len() >= capacity)
+        {
+            return Err(SError::ConnectionError(
+                "Offline write queue is full".to_owned(),
+            ));
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        self.pending.push_back((id, updates));
+        Ok(id)
+    }
+}
+
+/// How a successful write reached the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    /// The server accepted the update as the given packet.
+    Accepted(u32),
+    /// The server is unreachable; the update was queued as the given write id.
+    Queued(u64),
+}
+
+/// Packet received from the server whose update didn't fit the local state.
+/// The packet is skipped from that update on, alike on every replica.
+#[derive(Debug, Clone)]
+pub struct UpdateFailure {
+    pub packet_id: u32,
+    pub error: UpdateError,
+}
+
+/// Final outcome of a queued write, once it was replayed.
+#[derive(Debug)]
+pub struct WriteReport {
+    pub id: u64,
+    /// Packet id the update was accepted as.
+    pub result: Result<u32>,
+}
+
+pub(crate) enum ResponseType {
+    Accepted(u32),
+    Rejected(Rejection),
+    Head(u32),
+    Disconnected,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionStatus {
+    Connected,
+    /// The connection was lost and is being re-established.
+    Reconnecting,
+    /// Reconnecting gave up after `ReconnectPolicy::max_attempts` attempts.
+    Failed,
+}
+
+/// Exponential backoff used to re-establish a lost connection.
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    max_attempts: Option<u32>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(50),
+            max_backoff: Duration::from_secs(5),
+            max_attempts: None,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gives up as soon as the connection is lost.
+    pub fn disabled() -> Self {
+        Self::default().max_attempts(0)
+    }
+
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// Limits the number of reconnection attempts; unlimited by default.
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = Some(attempts);
+        self
+    }
+
+    /// Pause before reconnection attempt number `attempt`, counted from 1,
+    /// or `None` once the policy gives up.
+    pub(crate) fn delay(&self, attempt: u32) -> Option<Duration> {
+        if self.max_attempts.is_some_and(|max| attempt > max) {
+            return None;
+        }
+        Some(
+            self.initial_backoff
+                .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+                .min(self.max_backoff),
+        )
+    }
+}
+
+/// Where and how clients connect to the server.
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    addresses: Vec<SocketAddr>,
+    reconnect_policy: ReconnectPolicy,
+}
+
+impl ClientConfig {
+    /// Connects to the first reachable of the resolved addresses,
+    /// e.g. `"example.com:7878"` or `"[::1]:7878"`.
+    pub fn new<A: ToSocketAddrs>(address: A) -> Result<Self> {
+        let addresses: Vec<_> = address
+            .to_socket_addrs()
+            .map_err(to_connection_error)?
+            .collect();
+        if addresses.is_empty() {
+            return Err(SError::ConnectionError(
+                "Server address didn't resolve".to_owned(),
+            ));
+        }
+        Ok(Self {
+            addresses,
+            reconnect_policy: ReconnectPolicy::default(),
+        })
+    }
+
+    /// Connects to `127.0.0.1:{port}`.
+    pub fn local(port: u16) -> Self {
+        Self {
+            addresses: vec![SocketAddr::from((Ipv4Addr::LOCALHOST, port))],
+            reconnect_policy: ReconnectPolicy::default(),
+        }
+    }
+
+    pub fn reconnect_policy(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect_policy = policy;
+        self
+    }
+
+    pub(crate) fn addresses(&self) -> &[SocketAddr] {
+        &self.addresses
+    }
+
+    pub(crate) fn policy(&self) -> &ReconnectPolicy {
+        &self.reconnect_policy
+    }
+}
+
+/// How writes rejected as stale are retried, with exponential backoff between attempts.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    max_retries: Option<u32>,
 }
 
-enum ResponseType {
-    Accepted,
-    Rejected,
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(100),
+            max_retries: Some(16),
+        }
+    }
 }
 
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Retries until the server accepts the write.
+    pub fn unbounded() -> Self {
+        Self {
+            max_retries: None,
+            ..Self::default()
+        }
+    }
+
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// Limits the number of retries; 16 by default.
+    pub fn max_retries(mut self, retries: u32) -> Self {
+        self.max_retries = Some(retries);
+        self
+    }
+
+    /// Pause before retry number `attempt`, counted from 1.
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+            .min(self.max_backoff)
+    }
+
+    pub(crate) fn exhausted(&self, attempts: u32) -> bool {
+        self.max_retries.is_some_and(|retries| attempts > retries)
+    }
+}
+
+#[derive(Debug, Clone, Error)]
 pub enum SError {
+    #[error("Failed to connect: {0}")]
     ConnectionError(String),
+    /// The connection to the server was lost, or isn't established.
+    #[error("Disconnected: {0}")]
+    DisconnectedError(String),
+    /// The server refused the change for the given reason.
+    #[error("Change rejected: {0:?}")]
+    RejectedError(RejectionReason),
+    #[error("Server failure: {0}")]
     ServerError(String),
+    /// The server sent a message the client didn't expect.
+    #[error("Protocol violation: {0}")]
+    ProtocolError(String),
+    /// A message, update or state couldn't be encoded or decoded.
+    #[error("Serialization failed: {0}")]
+    SerializationError(String),
+    #[error("Internal error: {0}")]
     InternalError(String),
+    /// The server didn't respond, or the awaited packet wasn't applied, in time.
+    #[error("Timed out: {0}")]
+    TimeoutError(String),
+    /// The accepted update, or an update of the transaction, didn't fit the state,
+    /// so no replica applied any of it.
+    #[error("Update doesn't fit the state: {0}")]
+    ApplyError(UpdateError),
+    /// Other clients kept changing the state, exhausting the retries.
+    #[error("Too much contention: {0}")]
+    ContentionError(String),
+    /// A panic, e.g. in a closure passed to `update_with`, left the client's state unusable.
+    #[error("Lock poisoned: {0}")]
+    LockError(String),
 }
 pub type Result<T> = result::Result<T, SError>;
 
-fn to_connection_error<T: ToString>(error: T) -> SError {
+pub(crate) fn to_connection_error<T: ToString>(error: T) -> SError {
     SError::ConnectionError(error.to_string())
 }
 
-fn to_internal_error<T: ToString>(error: T) -> SError {
+pub(crate) fn to_disconnected_error<T: ToString>(error: T) -> SError {
+    SError::DisconnectedError(error.to_string())
+}
+
+pub(crate) fn to_lock_error<T: ToString>(error: T) -> SError {
+    SError::LockError(error.to_string())
+}
+
+pub(crate) fn to_protocol_error<T: ToString>(error: T) -> SError {
+    SError::ProtocolError(error.to_string())
+}
+
+pub(crate) fn to_serialization_error<T: ToString>(error: T) -> SError {
+    SError::SerializationError(error.to_string())
+}
+
+pub(crate) fn to_internal_error<T: ToString>(error: T) -> SError {
     SError::InternalError(error.to_string())
 }
 
+/// Stale packet ids are retried, every other rejection is final.
+pub(crate) fn rejected(rejection: Rejection) -> Result<()> {
+    match rejection.reason {
+        RejectionReason::StalePacketId => Ok(()),
+        reason => Err(SError::RejectedError(reason)),
+    }
+}
+
+/// Packet carrying `updates`: a plain update, or a transaction unless there's exactly one.
+pub(crate) fn update_message<U: Serialize>(
+    group_id: u32,
+    packet_id: u32,
+    updates: &[U],
+) -> Result<UMessage> {
+    match updates {
+        [update] => UMessage::new(group_id, packet_id, update).map_err(to_serialization_error),
+        updates => {
+            UMessage::transaction(group_id, packet_id, updates).map_err(to_serialization_error)
+        }
+    }
+}
+
+pub(crate) fn connection_lost() -> SError {
+    SError::DisconnectedError("Connection to the server was lost".to_owned())
+}
+
+pub(crate) fn contended(attempts: u32) -> SError {
+    SError::ContentionError(format!("Update rejected as stale {} times", attempts))
+}
+
+pub(crate) fn unexpected_response() -> SError {
+    SError::ProtocolError("Unexpected response from the server".to_owned())
+}
+
+pub(crate) fn timed_out(packet_id: u32) -> SError {
+    SError::TimeoutError(format!("Packet {} wasn't applied in time", packet_id))
+}
+
+/// Packet number of the local state once packet `packet_id` is applied.
+/// Servers accept no packet with the last id, so it has no successor.
+pub(crate) fn applied_after(packet_id: u32) -> Result<u32> {
+    packet_id
+        .checked_add(1)
+        .ok_or_else(|| SError::ProtocolError(format!("Packet id {} is out of range", packet_id)))
+}
+
+pub(crate) fn deadline_passed() -> SError {
+    SError::TimeoutError("The request didn't complete in time".to_owned())
+}
+
 fn send_client_message<W: Write>(message: ClientMessage, writer: &mut W) -> Result<()> {
-    let serialized = to_vec(&message).map_err(to_internal_error)?;
+    let serialized = to_vec(&message).map_err(to_serialization_error)?;
     let mut framed = BytesMut::new();
     LengthDelimitedCodec::new()
         .encode(serialized.into(), &mut framed)
-        .unwrap();
-    writer.write_all(&framed).map_err(to_internal_error)?;
-    writer.flush().map_err(to_internal_error)?;
+        .map_err(to_serialization_error)?;
+    writer.write_all(&framed).map_err(to_disconnected_error)?;
+    writer.flush().map_err(to_disconnected_error)?;
     Ok(())
 }
 
-fn stream_server_messages<R: Read>(reader: R, sender: Sender<ServerMessage>) -> Result<()> {
+/// Forwards the messages read from `reader` until it fails, forwarding the error too
+/// unless the connection was just lost.
+fn stream_server_messages<R: Read>(reader: R, sender: Sender<Result<ServerMessage>>) -> Result<()> {
     let mut codec = LengthDelimitedCodec::new();
     let mut buffer = BytesMut::new();
     let mut reader = reader;
@@ -65,7 +464,9 @@ fn stream_server_messages<R: Read>(reader: R, sender: Sender<ServerMessage>) ->
     let mut temp_buffer = [0; 1024];
     loop {
         let status = (|| -> Result<()> {
-            let bytes_read = reader.read(&mut temp_buffer).map_err(to_internal_error)?;
+            let bytes_read = reader
+                .read(&mut temp_buffer)
+                .map_err(to_disconnected_error)?;
             if bytes_read == 0 {
                 return Err(SError::ServerError("Connection closed".to_owned()));
             }
@@ -73,11 +474,11 @@ fn stream_server_messages<R: Read>(reader: R, sender: Sender<ServerMessage>) ->
             buffer.extend_from_slice(&temp_buffer[..bytes_read]);
 
             loop {
-                let frame = codec.decode(&mut buffer).map_err(to_internal_error)?;
+                let frame = codec.decode(&mut buffer).map_err(to_protocol_error)?;
                 if let Some(frame) = frame {
                     let message: ServerMessage =
-                        serde_json::from_slice(&frame).map_err(to_internal_error)?;
-                    sender.send(message).map_err(to_internal_error)?;
+                        serde_json::from_slice(&frame).map_err(to_serialization_error)?;
+                    sender.send(Ok(message)).map_err(to_internal_error)?;
                 } else {
                     break;
                 }
@@ -85,138 +486,687 @@ fn stream_server_messages<R: Read>(reader: R, sender: Sender<ServerMessage>) ->
             Ok(())
         })();
         if let Err(error) = status {
+            if let SError::ProtocolError(_) | SError::SerializationError(_) = error {
+                let _ = sender.send(Err(error.clone()));
+            }
             drop(sender);
             return Err(error);
         }
     }
 }
 
-impl<T> Synchronizer<T>
-where
-    T: Updatable + Default + Serialize + for<'de> Deserialize<'de> + Send + 'static,
-    <T as Updatable>::Update: Serialize + for<'de> Deserialize<'de> + Send + 'static,
-{
-    pub fn new(port: u16, group: u32) -> Result<Self> {
-        let inner = Arc::new(Mutex::new(T::default()));
-        let tcp_stream =
-            TcpStream::connect(format!("127.0.0.1:{}", port)).map_err(to_connection_error)?;
-        let last_packet_number = Arc::new(AtomicU32::new(0));
-        let (server_message_sender, server_message_receiver) = channel();
-        {
-            let tcp_stream = tcp_stream.try_clone().map_err(to_internal_error)?;
-            thread::spawn(|| stream_server_messages(tcp_stream, server_message_sender));
-        };
+/// Connects to the server and joins `group`, resuming from `resume_from` if given.
+/// Returns the stream, the messages read from it and the server's join response.
+fn connect(
+    addresses: &[SocketAddr],
+    group: u32,
+    resume_from: Option<u32>,
+) -> Result<(
+    TcpStream,
+    mpsc::Receiver<Result<ServerMessage>>,
+    ServerMessage,
+)> {
+    let tcp_stream = TcpStream::connect(addresses).map_err(to_connection_error)?;
+    let (server_message_sender, server_message_receiver) = channel();
+    {
+        let tcp_stream = tcp_stream.try_clone().map_err(to_internal_error)?;
+        thread::spawn(|| stream_server_messages(tcp_stream, server_message_sender));
+    };
+    let join_response = (|| {
         {
             let mut tcp_stream = &tcp_stream;
-            send_client_message(ClientMessage::JoinGroup(group), &mut tcp_stream)
+            send_client_message(
+                ClientMessage::JoinGroup(group, resume_from),
+                &mut tcp_stream,
+            )
         }?;
-        {
-            let message = server_message_receiver.recv().map_err(to_internal_error)?;
-            match message {
-                ServerMessage::Correct => {
-                    dbg!("Connected to the server");
-                    Ok(())
+        let message = server_message_receiver
+            .recv()
+            .map_err(to_connection_error)??;
+        match message {
+            ServerMessage::Correct | ServerMessage::OffsetUnavailable => Ok(message),
+            _ => Err(SError::ProtocolError(
+                "Server didn't accept join request".to_owned(),
+            )),
+        }
+    })();
+    match join_response {
+        Ok(message) => Ok((tcp_stream, server_message_receiver, message)),
+        Err(error) => {
+            let _ = tcp_stream.shutdown(std::net::Shutdown::Both);
+            Err(error)
+        }
+    }
+}
+
+/// Applies messages from the server to the local state and turns them into
+/// responses to the request in flight.
+pub(crate) struct MessageHandler {
+    /// Stale-packet rejection held back until the local state reaches its head.
+    pending_rejection: Option<Rejection>,
+}
+
+impl MessageHandler {
+    pub(crate) fn new() -> Self {
+        Self {
+            pending_rejection: None,
+        }
+    }
+
+    pub(crate) fn handle<T>(
+        &mut self,
+        message: ServerMessage,
+        replica: &Replica<T>,
+    ) -> Result<Option<ResponseType>>
+    where
+        T: Updatable + Clone + Default + for<'de> Deserialize<'de>,
+        <T as Updatable>::Update: for<'de> Deserialize<'de>,
+    {
+        match message {
+            ServerMessage::Update(umessage) => {
+                dbg!("Received update");
+                let applied_up_to = applied_after(umessage.packet_id)?;
+                let updates: Vec<T::Update> =
+                    umessage.get_updates().map_err(to_serialization_error)?;
+                let mut inner = replica.lock();
+                let mut interested = vec![];
+                // A transaction is applied to a copy, swapped in only if every update fits.
+                let mut state = match updates.len() {
+                    1 => None,
+                    _ => Some(inner.clone()),
+                };
+                for update in updates {
+                    let target = state.as_mut().unwrap_or(&mut *inner);
+                    let ids = replica.subscriptions().interested(&update, target);
+                    if let Err(error) = target.try_apply_update(update) {
+                        replica.failed(umessage.packet_id, error);
+                        interested.clear();
+                        state = None;
+                        break;
+                    }
+                    interested.push(ids);
+                }
+                if let Some(state) = state {
+                    *inner = state;
                 }
-                _ => Err(SError::ConnectionError(
-                    "Server didn't accept join request".to_owned(),
-                )),
+                let mut notified = Ok(());
+                replica.applied(inner, applied_up_to, |subscriptions| {
+                    notified = interested.iter().enumerate().try_for_each(|(index, ids)| {
+                        subscriptions.notify(ids, || {
+                            let mut updates =
+                                umessage.get_updates().map_err(to_serialization_error)?;
+                            Ok(Change::Update {
+                                packet_id: umessage.packet_id,
+                                update: updates.swap_remove(index),
+                            })
+                        })
+                    })
+                });
+                notified?;
+                Ok(self.release_rejection(applied_up_to))
             }
-        }?;
+            ServerMessage::Snapshot(snapshot) => {
+                let state = snapshot.get_state().map_err(to_serialization_error)?;
+                let mut inner = replica.lock();
+                *inner = state;
+                replica.applied(inner, snapshot.packet_id, |subscriptions| {
+                    subscriptions.replaced(snapshot.packet_id)
+                });
+                Ok(self.release_rejection(snapshot.packet_id))
+            }
+            ServerMessage::OffsetUnavailable => {
+                replica.reset();
+                Ok(None)
+            }
+            ServerMessage::Correct => Ok(None),
+            ServerMessage::Accepted(packet_id) => Ok(Some(ResponseType::Accepted(packet_id))),
+            ServerMessage::Rejected(rejection) => {
+                self.pending_rejection = Some(rejection);
+                Ok(self.release_rejection(replica.last_packet_number()))
+            }
+            ServerMessage::Head(head) => Ok(Some(ResponseType::Head(head))),
+        }
+    }
+
+    /// Releases the pending rejection once every update below its head was applied,
+    /// so that a retry is based on the server's current state.
+    fn release_rejection(&mut self, applied_up_to: u32) -> Option<ResponseType> {
+        match &self.pending_rejection {
+            Some(rejection)
+                if rejection.reason != RejectionReason::StalePacketId
+                    || rejection.head <= applied_up_to =>
+            {
+                self.pending_rejection.take().map(ResponseType::Rejected)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<T> Shared<T>
+where
+    T: Updatable + Clone + Default + Serialize + for<'de> Deserialize<'de> + Send + 'static,
+    <T as Updatable>::Update: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+{
+    fn set_status(&self, status: ConnectionStatus) {
+        if let Ok(mut current) = self.status.lock() {
+            *current = status;
+        }
+    }
+
+    fn send(&self, message: ClientMessage) -> Result<()> {
+        let connection = self.connection.lock().map_err(to_lock_error)?;
+        match connection.as_ref() {
+            Some(tcp_stream) => {
+                let mut tcp_stream = tcp_stream;
+                send_client_message(message, &mut tcp_stream)
+            }
+            None => Err(self.failure().unwrap_or_else(|| {
+                SError::DisconnectedError("Not connected to the server".to_owned())
+            })),
+        }
+    }
+
+    /// Error that ended the connection for good, if any.
+    fn failure(&self) -> Option<SError> {
+        self.failure
+            .lock()
+            .map_or_else(|e| Some(to_lock_error(e)), |failure| failure.clone())
+    }
+
+    /// Error of a request whose connection was lost.
+    fn lost(&self) -> SError {
+        self.failure().unwrap_or_else(connection_lost)
+    }
+
+    /// Sends `message` and receives the response to it, failing once `deadline` passes.
+    fn request(
+        &self,
+        responses: &mut Responses,
+        message: ClientMessage,
+        deadline: Option<Instant>,
+    ) -> Result<ResponseType> {
+        self.send(message)?;
+        responses.outstanding += 1;
+        responses.receive(deadline)
+    }
+
+    /// End of the time limit of a request starting now.
+    fn deadline(&self) -> Result<Option<Instant>> {
+        Ok(self
+            .timeout
+            .lock()
+            .map_err(to_lock_error)?
+            .map(|timeout| Instant::now() + timeout))
+    }
+
+    /// Sends `updates` as one packet until the server accepts it, returning its packet id
+    /// once the update was applied to the local state, or once accepted if the connection
+    /// is lost before.
+    fn publish(&self, responses: &mut Responses, updates: &[T::Update]) -> Result<u32> {
+        self.publish_with(responses, |packet_id, _| {
+            update_message(self.group_id, packet_id, updates).map(Some)
+        })?
+        .ok_or_else(|| SError::InternalError("No update to publish".to_owned()))
+    }
+
+    /// Sends the packet built by `message` from the local state until the server accepts it,
+    /// rebuilding it after every stale-packet rejection as the retry policy allows.
+    /// Returns `None` if `message` builds no packet.
+    fn publish_with<F>(&self, responses: &mut Responses, mut message: F) -> Result<Option<u32>>
+    where
+        F: FnMut(u32, &T) -> Result<Option<UMessage>>,
+    {
+        responses.discard_stale();
+        let epoch = self.replica.connection_epoch();
+        let deadline = self.deadline()?;
+        let policy = self.retry_policy.lock().map_err(to_lock_error)?.clone();
+        let mut attempts = 0;
+        loop {
+            let umessage = {
+                let inner = self.replica.lock();
+                match message(self.replica.last_packet_number(), &inner)? {
+                    Some(umessage) => umessage,
+                    None => return Ok(None),
+                }
+            };
+            match self.request(responses, ClientMessage::Update(umessage), deadline)? {
+                ResponseType::Accepted(packet_id) => {
+                    return match self.replica.wait_until_applied(
+                        applied_after(packet_id)?,
+                        Some(epoch),
+                        deadline,
+                    ) {
+                        Wait::Applied => match self.replica.take_failure(packet_id) {
+                            Some(error) => Err(SError::ApplyError(error)),
+                            None => Ok(Some(packet_id)),
+                        },
+                        // The packet is committed; it's applied once reconnected.
+                        Wait::Interrupted => Ok(Some(packet_id)),
+                        Wait::TimedOut => Err(timed_out(packet_id)),
+                    };
+                }
+                ResponseType::Rejected(rejection) => rejected(rejection)?,
+                ResponseType::Head(_) => return Err(unexpected_response()),
+                ResponseType::Disconnected => return Err(self.lost()),
+            }
+            attempts += 1;
+            if policy.exhausted(attempts) {
+                return Err(contended(attempts));
+            }
+            let backoff = policy.backoff(attempts);
+            if deadline.is_some_and(|deadline| Instant::now() + backoff >= deadline) {
+                return Err(deadline_passed());
+            }
+            thread::sleep(backoff);
+        }
+    }
+
+    /// Publishes queued writes in order, stopping if the connection is lost again.
+    fn replay_offline_writes(&self) {
+        while !self.closed.load(Ordering::Relaxed) {
+            let Ok(mut responses) = self.responses.lock() else {
+                return;
+            };
+            let (id, updates) = {
+                let Ok(mut offline) = self.offline.lock() else {
+                    return;
+                };
+                match offline.pending.pop_front() {
+                    Some(write) => {
+                        offline.replaying = true;
+                        write
+                    }
+                    None => return,
+                }
+            };
+            let result = self.publish(&mut responses, &updates);
+            let Ok(mut offline) = self.offline.lock() else {
+                return;
+            };
+            offline.replaying = false;
+            match result {
+                Err(SError::DisconnectedError(_)) => {
+                    offline.pending.push_front((id, updates));
+                    return;
+                }
+                result => offline.reports.push(WriteReport { id, result }),
+            }
+        }
+    }
+
+    /// Applies updates from the server until the connection is lost, returning the error
+    /// that made its messages unusable, if any.
+    fn process_messages(
+        &self,
+        messages: &mpsc::Receiver<Result<ServerMessage>>,
+        response_sender: &Sender<ResponseType>,
+    ) -> Option<SError> {
+        let mut handler = MessageHandler::new();
+        loop {
+            let message = match messages.recv() {
+                Ok(Ok(message)) => message,
+                Ok(Err(error)) => return Some(error),
+                Err(_) => return None,
+            };
+            match handler.handle(message, &self.replica) {
+                Ok(Some(response)) => {
+                    if response_sender.send(response).is_err() {
+                        return None;
+                    }
+                }
+                Ok(None) => {}
+                Err(error) => return Some(error),
+            }
+        }
+    }
+
+    fn disconnect(&self) {
+        if let Some(tcp_stream) = self
+            .connection
+            .lock()
+            .ok()
+            .and_then(|mut connection| connection.take())
+        {
+            let _ = tcp_stream.shutdown(std::net::Shutdown::Both);
+        }
+    }
+
+    /// Re-establishes the connection, resuming from the last applied update.
+    /// Returns `None` once the policy gives up or the synchronizer is dropped.
+    fn reconnect(
+        &self,
+        config: &ClientConfig,
+        group: u32,
+    ) -> Option<mpsc::Receiver<Result<ServerMessage>>> {
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let backoff = config.reconnect_policy.delay(attempts)?;
+            if self.closed.load(Ordering::Relaxed) {
+                return None;
+            }
+            thread::sleep(backoff);
+
+            let resume_from = self.replica.last_packet_number();
+            let (tcp_stream, messages, join_response) =
+                match connect(&config.addresses, group, Some(resume_from)) {
+                    Ok(connection) => connection,
+                    Err(_) => continue,
+                };
+            if let ServerMessage::OffsetUnavailable = join_response {
+                self.replica.reset();
+            }
+
+            let mut connection = match self.connection.lock() {
+                Ok(connection) if !self.closed.load(Ordering::Relaxed) => connection,
+                _ => {
+                    let _ = tcp_stream.shutdown(std::net::Shutdown::Both);
+                    return None;
+                }
+            };
+            *connection = Some(tcp_stream);
+            return Some(messages);
+        }
+    }
+}
+
+impl<T> Synchronizer<T>
+where
+    T: Updatable + Clone + Default + Serialize + for<'de> Deserialize<'de> + Send + 'static,
+    <T as Updatable>::Update: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+{
+    pub fn new(port: u16, group: u32) -> Result<Self> {
+        Self::with_config(ClientConfig::local(port), group)
+    }
+
+    pub fn with_reconnect_policy(port: u16, group: u32, policy: ReconnectPolicy) -> Result<Self> {
+        Self::with_config(ClientConfig::local(port).reconnect_policy(policy), group)
+    }
+
+    pub fn with_config(config: ClientConfig, group: u32) -> Result<Self> {
+        let (tcp_stream, messages, _) = connect(&config.addresses, group, None)?;
         let (response_sender, response_receiver) = channel();
-        let result = {
-            let connection = tcp_stream.try_clone().map_err(to_internal_error)?;
-            Synchronizer {
-                inner: inner.clone(),
-                last_packet_number: last_packet_number.clone(),
-                connection,
-                group_id: group,
+        let shared = Arc::new(Shared {
+            replica: Replica::new(),
+            group_id: group,
+            connection: Mutex::new(Some(tcp_stream)),
+            responses: Mutex::new(Responses {
                 receiver: response_receiver,
-            }
+                outstanding: 0,
+            }),
+            status: Mutex::new(ConnectionStatus::Connected),
+            offline: Mutex::new(OfflineQueue::new()),
+            retry_policy: Mutex::new(RetryPolicy::default()),
+            timeout: Mutex::new(None),
+            failure: Mutex::new(None),
+            closed: AtomicBool::new(false),
+        });
+        let result = Synchronizer {
+            shared: shared.clone(),
         };
         thread::spawn(move || {
-            let mut can_send_rejected = true;
-            let mut should_send_reject_after_update = false;
+            let mut messages = messages;
             loop {
-                let status = (|| -> Result<()> {
-                    let message = server_message_receiver.recv().map_err(to_internal_error)?;
-                    match message {
-                        ServerMessage::Update(umessage) => {
-                            dbg!("Received update");
-                            last_packet_number.store(
-                                umessage.packet_id + 1,
-                                std::sync::atomic::Ordering::Relaxed,
-                            );
-                            let update = umessage.get_update().map_err(to_internal_error)?;
-                            inner.lock().unwrap().apply_update(update);
-                            if should_send_reject_after_update {
-                                response_sender
-                                    .send(ResponseType::Rejected)
-                                    .map_err(to_internal_error)
-                            } else {
-                                can_send_rejected = true;
-                                Ok(())
-                            }
-                        }
-                        ServerMessage::Correct => {
-                            dbg!("Received Correct");
-                            response_sender
-                                .send(ResponseType::Accepted)
-                                .map_err(to_internal_error)
-                        }
-                        ServerMessage::Error => {
-                            dbg!("Received Error");
-                            if can_send_rejected {
-                                can_send_rejected = false;
-                                response_sender
-                                    .send(ResponseType::Rejected)
-                                    .map_err(to_internal_error)
-                            } else {
-                                should_send_reject_after_update = true;
-                                Ok(())
-                            }
+                let failure = shared.process_messages(&messages, &response_sender);
+                shared.disconnect();
+                // Reconnecting would resume from the same offset and fail again.
+                let failed = failure.is_some();
+                if let Ok(mut current) = shared.failure.lock() {
+                    *current = failure;
+                }
+                shared.replica.interrupt();
+                if shared.closed.load(Ordering::Relaxed) {
+                    break;
+                }
+                if failed {
+                    shared.set_status(ConnectionStatus::Failed);
+                    let _ = response_sender.send(ResponseType::Disconnected);
+                    break;
+                }
+                let _ = response_sender.send(ResponseType::Disconnected);
+                shared.set_status(ConnectionStatus::Reconnecting);
+                match shared.reconnect(&config, group) {
+                    Some(new_messages) => {
+                        messages = new_messages;
+                        shared.set_status(ConnectionStatus::Connected);
+                        if shared
+                            .offline
+                            .lock()
+                            .is_ok_and(|offline| !offline.pending.is_empty())
+                        {
+                            let shared = shared.clone();
+                            thread::spawn(move || shared.replay_offline_writes());
                         }
                     }
-                })();
-                if status.is_err() {
-                    let _ = tcp_stream.shutdown(std::net::Shutdown::Both);
-                    break;
+                    None => {
+                        shared.set_status(ConnectionStatus::Failed);
+                        break;
+                    }
                 }
             }
         });
         Ok(result)
     }
 
-    pub fn publish_update(&mut self, update: T::Update) -> Result<()> {
-        loop {
-            let packet_id = self
-                .last_packet_number
-                .load(std::sync::atomic::Ordering::Relaxed);
-            let group_id = self.group_id;
-            let umessage = UMessage::new(group_id, packet_id, &update).unwrap();
-            let message = ClientMessage::Update(umessage);
-            let mut tcp_stream = &self.connection;
-            send_client_message(message, &mut tcp_stream)?;
-            match self.receiver.recv() {
-                Ok(response) => {
-                    if let ResponseType::Accepted = response {
-                        return Ok(());
-                    }
-                }
-                Err(error) => {
-                    return Err(to_internal_error(error));
-                }
+    /// Queues up to `capacity` writes made while the server is unreachable,
+    /// instead of failing them. Queued writes are replayed in order once reconnected.
+    pub fn with_offline_queue(self, capacity: usize) -> Self {
+        if let Ok(mut offline) = self.shared.offline.lock() {
+            offline.capacity = Some(capacity);
+        }
+        self
+    }
+
+    /// Retries writes rejected as stale according to `policy` instead of the default one.
+    pub fn with_retry_policy(self, policy: RetryPolicy) -> Self {
+        if let Ok(mut current) = self.shared.retry_policy.lock() {
+            *current = policy;
+        }
+        self
+    }
+
+    /// Fails requests to the server with a `TimeoutError` once they take longer than `timeout`.
+    /// A write that timed out may still be applied later.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.set_timeout(Some(timeout));
+        self
+    }
+
+    /// Time limit of requests to the server; unlimited by default.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.shared.timeout.lock().ok().and_then(|timeout| *timeout)
+    }
+
+    pub fn set_timeout(&self, timeout: Option<Duration>) {
+        if let Ok(mut current) = self.shared.timeout.lock() {
+            *current = timeout;
+        }
+    }
+
+    /// Runs `f` with requests limited to `timeout`, restoring the previous limit afterwards.
+    pub fn within<R>(&mut self, timeout: Duration, f: impl FnOnce(&mut Self) -> R) -> R {
+        let previous = self.timeout();
+        self.set_timeout(Some(timeout));
+        let result = f(self);
+        self.set_timeout(previous);
+        result
+    }
+
+    /// Publishes `update`, retrying as the retry policy allows until the server accepts it.
+    /// Returns once the update was applied locally, so `get_lock` observes it, unless the
+    /// connection is lost after the server accepted it: it's then applied once reconnected.
+    /// With an offline queue, writes made while disconnected (or while older queued writes
+    /// are still pending) are queued instead.
+    pub fn publish_update(&mut self, update: T::Update) -> Result<Delivery> {
+        self.publish_updates(vec![update])
+    }
+
+    /// Publishes the update computed by `f` from the local state, recomputing it against the
+    /// caught-up state whenever the server rejects it as stale, as the retry policy allows.
+    /// Returns `None` without publishing if `f` does. Bypasses the offline queue.
+    pub fn update_with<F>(&mut self, mut f: F) -> Result<Option<Delivery>>
+    where
+        F: FnMut(&T) -> Option<T::Update>,
+    {
+        let mut responses = self.shared.responses.lock().map_err(to_lock_error)?;
+        let group_id = self.shared.group_id;
+        self.shared
+            .publish_with(&mut responses, |packet_id, state| {
+                f(state)
+                    .map(|update| UMessage::new(group_id, packet_id, &update))
+                    .transpose()
+                    .map_err(to_serialization_error)
+            })
+            .map(|packet_id| packet_id.map(Delivery::Accepted))
+    }
+
+    /// Publishes `updates` as one packet, which the server accepts or rejects as a whole
+    /// and every replica applies atomically.
+    pub fn publish_transaction(&mut self, updates: Vec<T::Update>) -> Result<Delivery> {
+        if updates.is_empty() {
+            return Err(SError::InternalError("Empty transaction".to_owned()));
+        }
+        self.publish_updates(updates)
+    }
+
+    fn publish_updates(&mut self, updates: Vec<T::Update>) -> Result<Delivery> {
+        let mut responses = self.shared.responses.lock().map_err(to_lock_error)?;
+        {
+            let mut offline = self.shared.offline.lock().map_err(to_lock_error)?;
+            if let Some(failure) = self.shared.failure() {
+                return Err(failure);
+            }
+            if offline.capacity.is_some()
+                && (self.status() != ConnectionStatus::Connected || !offline.pending.is_empty())
+            {
+                return offline.enqueue(updates).map(Delivery::Queued);
             }
         }
+        self.shared
+            .publish(&mut responses, &updates)
+            .map(Delivery::Accepted)
+    }
+
+    /// Number of queued writes not replayed yet.
+    pub fn pending_writes(&self) -> usize {
+        self.shared.offline.lock().map_or(0, |offline| {
+            offline.pending.len() + offline.replaying as usize
+        })
+    }
+
+    /// Packets that didn't fit the local state since the last call,
+    /// except the ones already reported to the call that published them.
+    pub fn take_update_failures(&self) -> Vec<UpdateFailure> {
+        self.shared.replica.take_failures()
+    }
+
+    /// Outcomes of queued writes replayed since the last call.
+    pub fn take_write_reports(&self) -> Vec<WriteReport> {
+        self.shared
+            .offline
+            .lock()
+            .map(|mut offline| std::mem::take(&mut offline.reports))
+            .unwrap_or_default()
     }
 }
 
 impl<T> Synchronizer<T>
 where
-    T: Updatable + Default + Serialize + for<'de> Deserialize<'de> + Send + 'static,
+    T: Updatable + Clone + Default + Serialize + for<'de> Deserialize<'de> + Send + 'static,
     <T as Updatable>::Update: Serialize + for<'de> Deserialize<'de> + Send + 'static,
 {
+    /// Uploads the current state, letting the server drop the history it covers.
+    /// Clients joining later start from this snapshot instead of replaying every update.
+    pub fn publish_snapshot(&mut self) -> Result<()> {
+        let mut responses = self.shared.responses.lock().map_err(to_lock_error)?;
+        responses.discard_stale();
+        let deadline = self.shared.deadline()?;
+        let snapshot = {
+            let inner = self.get_lock();
+            let packet_id = self.shared.replica.last_packet_number();
+            USnapshot::new(self.shared.group_id, packet_id, &*inner)
+                .map_err(to_serialization_error)?
+        };
+        match self
+            .shared
+            .request(&mut responses, ClientMessage::Snapshot(snapshot), deadline)?
+        {
+            ResponseType::Accepted(_) => Ok(()),
+            ResponseType::Rejected(rejection) => Err(SError::RejectedError(rejection.reason)),
+            ResponseType::Head(_) => Err(unexpected_response()),
+            ResponseType::Disconnected => Err(self.shared.lost()),
+        }
+    }
+
+    /// Blocks until every update the server had accepted at call time was applied locally.
+    pub fn sync(&self) -> Result<()> {
+        let mut responses = self.shared.responses.lock().map_err(to_lock_error)?;
+        responses.discard_stale();
+        let epoch = self.shared.replica.connection_epoch();
+        let deadline = self.shared.deadline()?;
+        let head = match self
+            .shared
+            .request(&mut responses, ClientMessage::Head, deadline)?
+        {
+            ResponseType::Head(head) => head,
+            ResponseType::Disconnected => return Err(self.shared.lost()),
+            _ => return Err(unexpected_response()),
+        };
+        match self
+            .shared
+            .replica
+            .wait_until_applied(head, Some(epoch), deadline)
+        {
+            Wait::Applied => Ok(()),
+            Wait::Interrupted => Err(self.shared.lost()),
+            Wait::TimedOut => Err(deadline_passed()),
+        }
+    }
+
+    /// Blocks for at most `timeout` until packet `packet_id` was applied locally,
+    /// e.g. one returned by `Delivery::Accepted`. Keeps waiting across reconnections.
+    pub fn wait_for(&self, packet_id: u32, timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        let applied_up_to = applied_after(packet_id)?;
+        match self
+            .shared
+            .replica
+            .wait_until_applied(applied_up_to, None, Some(deadline))
+        {
+            Wait::Applied => Ok(()),
+            _ => Err(timed_out(packet_id)),
+        }
+    }
+
+    /// Number of updates applied to the local state; packet `n` is applied once it exceeds `n`.
+    pub fn version(&self) -> u32 {
+        self.shared.replica.last_packet_number()
+    }
+
+    /// Subscribes to the changes applied to the local state.
+    pub fn subscribe(&self) -> Subscribe<'_, T> {
+        Subscribe::new(self.shared.replica.subscriptions_mutex())
+    }
+
+    pub fn unsubscribe(&self, id: SubscriptionId) {
+        self.shared.replica.subscriptions().remove(id);
+    }
+
+    /// Watches the changes touching a subtree of the local state.
+    pub fn watch(&self) -> Watch<'_, T, T> {
+        Watch::new(self.shared.replica.subscriptions_mutex())
+    }
+
     pub fn get_lock(&self) -> std::sync::MutexGuard<'_, T> {
-        self.inner.lock().unwrap()
+        self.shared.replica.lock()
+    }
+
+    pub fn status(&self) -> ConnectionStatus {
+        self.shared
+            .status
+            .lock()
+            .map_or(ConnectionStatus::Failed, |status| *status)
     }
 }
 
@@ -226,6 +1176,10 @@ where
     <T as Updatable>::Update: Serialize,
 {
     fn drop(&mut self) {
-        let _ = self.connection.shutdown(std::net::Shutdown::Both);
+        let connection = self.shared.connection.lock();
+        self.shared.closed.store(true, Ordering::Relaxed);
+        if let Some(tcp_stream) = connection.ok().and_then(|mut connection| connection.take()) {
+            let _ = tcp_stream.shutdown(std::net::Shutdown::Both);
+        }
     }
 }
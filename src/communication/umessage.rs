@@ -5,6 +5,9 @@ pub struct UMessage {
     group_id: u32,
     pub packet_id: u32,
     pub update: String,
+    /// Whether `update` holds a list of updates applied atomically as one packet.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub transaction: bool,
 }
 
 impl UMessage {
@@ -17,6 +20,20 @@ impl UMessage {
             group_id,
             packet_id,
             update: serde_json::to_string(update)?,
+            transaction: false,
+        })
+    }
+
+    pub fn transaction<T: Serialize>(
+        group_id: u32,
+        packet_id: u32,
+        updates: &[T],
+    ) -> Result<Self, serde_json::Error> {
+        Ok(Self {
+            group_id,
+            packet_id,
+            update: serde_json::to_string(updates)?,
+            transaction: true,
         })
     }
 }
@@ -25,4 +42,40 @@ impl<'a> UMessage {
     pub fn get_update<T: Deserialize<'a>>(&'a self) -> Result<T, serde_json::Error> {
         serde_json::from_str(&self.update)
     }
+
+    /// The updates of the packet, in order; a single one unless it's a transaction.
+    pub fn get_updates<T: Deserialize<'a>>(&'a self) -> Result<Vec<T>, serde_json::Error> {
+        match self.transaction {
+            true => serde_json::from_str(&self.update),
+            false => Ok(vec![self.get_update()?]),
+        }
+    }
+}
+
+/// Serialized state of a shared structure after applying every update below `packet_id`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct USnapshot {
+    group_id: u32,
+    pub packet_id: u32,
+    pub state: String,
+}
+
+impl USnapshot {
+    pub fn new<T: Serialize>(
+        group_id: u32,
+        packet_id: u32,
+        state: &T,
+    ) -> Result<Self, serde_json::Error> {
+        Ok(Self {
+            group_id,
+            packet_id,
+            state: serde_json::to_string(state)?,
+        })
+    }
+}
+
+impl<'a> USnapshot {
+    pub fn get_state<T: Deserialize<'a>>(&'a self) -> Result<T, serde_json::Error> {
+        serde_json::from_str(&self.state)
+    }
 }
@@ -0,0 +1,326 @@
+use crate::communication::umessage::{UMessage, USnapshot};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Every record is stored as `[payload length][crc32 of payload][payload]`.
+const HEADER_SIZE: usize = 8;
+const SEGMENT_EXTENSION: &str = "seg";
+const GROUP_DIR_PREFIX: &str = "group-";
+const SNAPSHOT_FILE: &str = "snapshot.json";
+const SNAPSHOT_TMP_FILE: &str = "snapshot.json.tmp";
+
+/// When appended records are flushed to stable storage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsyncPolicy {
+    /// `fsync` after every appended update.
+    Always,
+    /// `fsync` once every `n` appended updates.
+    Batched(usize),
+    /// Leave flushing to the operating system.
+    OsManaged,
+}
+
+#[derive(Debug, Clone)]
+pub struct LogConfig {
+    dir: PathBuf,
+    fsync: FsyncPolicy,
+    segment_size: u64,
+}
+
+impl LogConfig {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self {
+            dir: dir.into(),
+            fsync: FsyncPolicy::Always,
+            segment_size: 16 * 1024 * 1024,
+        }
+    }
+
+    pub fn fsync(mut self, policy: FsyncPolicy) -> Self {
+        self.fsync = policy;
+        self
+    }
+
+    /// Size in bytes after which a new segment is started.
+    pub fn segment_size(mut self, bytes: u64) -> Self {
+        self.segment_size = bytes;
+        self
+    }
+}
+
+/// Root of the on-disk log, holding one directory of segments per group.
+#[derive(Debug)]
+pub struct Wal {
+    config: LogConfig,
+}
+
+/// A group recovered from disk: its latest snapshot and the history following it.
+#[derive(Debug)]
+pub struct RecoveredGroup {
+    pub group_id: u32,
+    pub log: GroupLog,
+    pub snapshot: Option<USnapshot>,
+    pub history: Vec<UMessage>,
+}
+
+impl Wal {
+    pub fn open(config: LogConfig) -> io::Result<Self> {
+        fs::create_dir_all(&config.dir)?;
+        Ok(Self { config })
+    }
+
+    pub fn recover(&self) -> io::Result<Vec<RecoveredGroup>> {
+        let mut groups = vec![];
+        for entry in fs::read_dir(&self.config.dir)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let group_id = match name
+                .to_str()
+                .and_then(|name| name.strip_prefix(GROUP_DIR_PREFIX))
+                .and_then(|id| id.parse::<u32>().ok())
+            {
+                Some(group_id) => group_id,
+                None => continue,
+            };
+            let (log, snapshot, history) = GroupLog::recover(entry.path(), &self.config)?;
+            groups.push(RecoveredGroup {
+                group_id,
+                log,
+                snapshot,
+                history,
+            });
+        }
+        Ok(groups)
+    }
+
+    pub fn create_group(&self, group_id: u32) -> io::Result<GroupLog> {
+        let dir = self
+            .config
+            .dir
+            .join(format!("{}{}", GROUP_DIR_PREFIX, group_id));
+        fs::create_dir_all(&dir)?;
+        let (log, _, _) = GroupLog::recover(dir, &self.config)?;
+        Ok(log)
+    }
+}
+
+/// Append-only, segmented log of the updates accepted for a single group.
+#[derive(Debug)]
+pub struct GroupLog {
+    dir: PathBuf,
+    fsync: FsyncPolicy,
+    segment_size: u64,
+    /// First packet id and path of every segment, the last one being active.
+    segments: Vec<(u32, PathBuf)>,
+    active: File,
+    active_size: u64,
+    unsynced: usize,
+}
+
+fn segment_path(dir: &Path, first_packet_id: u32) -> PathBuf {
+    dir.join(format!("{:010}.{}", first_packet_id, SEGMENT_EXTENSION))
+}
+
+fn list_segments(dir: &Path) -> io::Result<Vec<(u32, PathBuf)>> {
+    let mut segments = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(SEGMENT_EXTENSION) {
+            continue;
+        }
+        let first_packet_id = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.parse::<u32>().ok())
+            .ok_or_else(|| corrupted(&path, "unexpected segment name"))?;
+        segments.push((first_packet_id, path));
+    }
+    segments.sort();
+    Ok(segments)
+}
+
+fn read_snapshot(dir: &Path) -> io::Result<Option<USnapshot>> {
+    let path = dir.join(SNAPSHOT_FILE);
+    match fs::read(&path) {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|_e| corrupted(&path, "invalid snapshot")),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn corrupted(path: &Path, reason: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}: {}", path.display(), reason),
+    )
+}
+
+/// Reads every intact record of a segment.
+/// Returns the records, the length of the valid prefix and whether a torn tail follows it.
+fn read_segment(path: &Path) -> io::Result<(Vec<UMessage>, u64, bool)> {
+    let bytes = fs::read(path)?;
+    let mut records = vec![];
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let rest = &bytes[offset..];
+        if rest.len() < HEADER_SIZE {
+            return Ok((records, offset as u64, true));
+        }
+        let length = u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        let checksum = u32::from_le_bytes([rest[4], rest[5], rest[6], rest[7]]);
+        let payload = match rest[HEADER_SIZE..].get(..length) {
+            Some(payload) if crc32fast::hash(payload) == checksum => payload,
+            _ => return Ok((records, offset as u64, true)),
+        };
+        let message = serde_json::from_slice(payload)
+            .map_err(|_e| corrupted(path, "record is not a valid update"))?;
+        records.push(message);
+        offset += HEADER_SIZE + length;
+    }
+    Ok((records, offset as u64, false))
+}
+
+impl GroupLog {
+    /// Opens the snapshot and segments in `dir`, dropping a torn record at the end of the last segment.
+    /// Updates already covered by the snapshot are skipped.
+    fn recover(
+        dir: PathBuf,
+        config: &LogConfig,
+    ) -> io::Result<(Self, Option<USnapshot>, Vec<UMessage>)> {
+        let snapshot = read_snapshot(&dir)?;
+        let mut segments = list_segments(&dir)?;
+        let mut next_packet_id = snapshot.as_ref().map_or(0, |snapshot| snapshot.packet_id);
+        let mut history: Vec<UMessage> = vec![];
+        let mut active = None;
+        for (index, (_, path)) in segments.iter().enumerate() {
+            let (records, valid_len, torn) = read_segment(path)?;
+            let is_last = index + 1 == segments.len();
+            if torn && !is_last {
+                return Err(corrupted(path, "checksum mismatch in sealed segment"));
+            }
+            for record in records {
+                if record.packet_id < next_packet_id && history.is_empty() {
+                    continue;
+                }
+                if record.packet_id != next_packet_id {
+                    return Err(corrupted(path, "non-contiguous packet ids"));
+                }
+                next_packet_id = next_packet_id
+                    .checked_add(1)
+                    .ok_or_else(|| corrupted(path, "packet id overflow"))?;
+                history.push(record);
+            }
+            if is_last {
+                let file = OpenOptions::new().append(true).open(path)?;
+                if torn {
+                    file.set_len(valid_len)?;
+                    file.sync_all()?;
+                }
+                active = Some((file, valid_len));
+            }
+        }
+
+        let (active, active_size) = match active {
+            Some(active) => active,
+            None => {
+                segments.push((next_packet_id, segment_path(&dir, next_packet_id)));
+                (Self::create_segment(&dir, next_packet_id)?, 0)
+            }
+        };
+        let log = Self {
+            dir,
+            fsync: config.fsync,
+            segment_size: config.segment_size,
+            segments,
+            active,
+            active_size,
+            unsynced: 0,
+        };
+        Ok((log, snapshot, history))
+    }
+
+    fn create_segment(dir: &Path, first_packet_id: u32) -> io::Result<File> {
+        let file = OpenOptions::new()
+            .create_new(true)
+            .append(true)
+            .open(segment_path(dir, first_packet_id))?;
+        File::open(dir)?.sync_all()?;
+        Ok(file)
+    }
+
+    pub fn append(&mut self, message: &UMessage) -> io::Result<()> {
+        if self.active_size >= self.segment_size {
+            self.active.sync_all()?;
+            self.active = Self::create_segment(&self.dir, message.packet_id)?;
+            self.segments.push((
+                message.packet_id,
+                segment_path(&self.dir, message.packet_id),
+            ));
+            self.active_size = 0;
+            self.unsynced = 0;
+        }
+
+        let payload = serde_json::to_vec(message)?;
+        let mut record = Vec::with_capacity(HEADER_SIZE + payload.len());
+        record.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        record.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+        record.extend_from_slice(&payload);
+
+        if let Err(e) = self.active.write_all(&record) {
+            let _ = self.active.set_len(self.active_size);
+            return Err(e);
+        }
+        self.active_size += record.len() as u64;
+        self.unsynced += 1;
+        self.sync_if_needed()
+    }
+
+    /// Durably replaces the stored snapshot, then deletes sealed segments it fully covers.
+    pub fn write_snapshot(&mut self, snapshot: &USnapshot) -> io::Result<()> {
+        let tmp_path = self.dir.join(SNAPSHOT_TMP_FILE);
+        {
+            let mut file = File::create(&tmp_path)?;
+            file.write_all(&serde_json::to_vec(snapshot)?)?;
+            file.sync_all()?;
+        }
+        fs::rename(&tmp_path, self.dir.join(SNAPSHOT_FILE))?;
+        File::open(&self.dir)?.sync_all()?;
+
+        // A segment is covered once the next one starts at or before the snapshot.
+        let covered = self
+            .segments
+            .windows(2)
+            .take_while(|pair| pair[1].0 <= snapshot.packet_id)
+            .count();
+        for (_, path) in self.segments.drain(..covered) {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    fn sync_if_needed(&mut self) -> io::Result<()> {
+        let should_sync = match self.fsync {
+            FsyncPolicy::Always => true,
+            FsyncPolicy::Batched(batch) => self.unsynced >= batch,
+            FsyncPolicy::OsManaged => false,
+        };
+        if should_sync {
+            self.active.sync_data()?;
+            self.unsynced = 0;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for GroupLog {
+    fn drop(&mut self) {
+        if self.unsynced > 0 && self.fsync != FsyncPolicy::OsManaged {
+            let _ = self.active.sync_data();
+        }
+    }
+}
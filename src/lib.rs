@@ -1,3 +1,6 @@
 pub mod communication;
 pub mod score;
 pub mod ucore;
+
+#[doc(hidden)]
+pub use serde;
@@ -0,0 +1,64 @@
+use crate::communication::async_synchronizer::{AsyncSynchronizer, PublishFuture};
+use crate::communication::subscription::Watch;
+use crate::communication::synchronizer::{self, Delivery};
+use crate::ucore::umap::{UMap, UMapUpdate};
+use crate::ucore::unested::UNested;
+use crate::ucore::updateable;
+use serde::{Deserialize, Serialize};
+use std::hash::Hash;
+use std::marker::PhantomData;
+use updateable::Updatable;
+
+pub struct AsyncSMap<K, T>
+where
+    K: Eq + Hash + Clone + Serialize,
+    T: Updatable + Clone + Serialize,
+    <T as Updatable>::Update: Serialize,
+{
+    syn: AsyncSynchronizer<UMap<K, T>>,
+}
+
+impl<K, T> AsyncSMap<K, T>
+where
+    K: Eq + Hash + Clone + Serialize + for<'de> Deserialize<'de> + Send + 'static,
+    T: Updatable + Clone + Serialize + for<'de> Deserialize<'de> + Send + 'static,
+    <T as Updatable>::Update: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+{
+    async_synchronizer_methods!(UMap<K, T>);
+
+    pub async fn insert(&mut self, key: K, value: T) -> synchronizer::Result<Delivery> {
+        self.syn
+            .publish_update(UMapUpdate::Insert(key, value))
+            .await
+    }
+
+    pub async fn remove(&mut self, key: K) -> synchronizer::Result<Delivery> {
+        self.syn.publish_update(UMapUpdate::Remove(key)).await
+    }
+
+    /// Watches the changes touching a subtree, e.g. `watch().key(k).index(3)`.
+    pub fn watch(&self) -> Watch<'_, UMap<K, T>, UMap<K, T>> {
+        self.syn.watch()
+    }
+
+    pub fn get(&self, key: &K) -> Option<T> {
+        self.syn.get_lock().get(key)
+    }
+
+    pub fn get_lock(&self) -> std::sync::MutexGuard<'_, UMap<K, T>> {
+        self.syn.get_lock()
+    }
+
+    /// Nested updates resolve to a future publishing them.
+    pub fn get_mut<'a>(
+        &'a mut self,
+        key: K,
+    ) -> UNested<T, PublishFuture<'a>, impl FnOnce(T::Update) -> PublishFuture<'a> + 'a> {
+        UNested {
+            apply_outer: move |update| -> PublishFuture<'a> {
+                Box::pin(self.syn.publish_update(UMapUpdate::Nested(key, update)))
+            },
+            inner_type: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use crate::communication::async_synchronizer::{AsyncSynchronizer, PublishFuture};
+use crate::communication::subscription::Watch;
+use crate::communication::synchronizer::{self, Delivery};
+use crate::ucore::unested::UNested;
+use crate::ucore::updateable;
+use crate::ucore::ustack::{UStack, UStackUpdate};
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+use updateable::Updatable;
+
+pub struct AsyncSStack<T>
+where
+    T: Updatable + Clone + Serialize,
+    <T as Updatable>::Update: Serialize,
+{
+    syn: AsyncSynchronizer<UStack<T>>,
+}
+
+impl<T> AsyncSStack<T>
+where
+    T: Updatable + Clone + Serialize + for<'de> Deserialize<'de> + Send + 'static,
+    <T as Updatable>::Update: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+{
+    async_synchronizer_methods!(UStack<T>);
+
+    pub async fn push(&mut self, value: T) -> synchronizer::Result<Delivery> {
+        self.syn.publish_update(UStackUpdate::Push(value)).await
+    }
+
+    pub async fn pop(&mut self) -> synchronizer::Result<Delivery> {
+        self.syn.publish_update(UStackUpdate::Pop).await
+    }
+
+    /// Watches the changes touching a subtree, e.g. the top of the stack:
+    ///
+    /// ```no_run
+    /// # use shared_state_machine::score::async_sstack::AsyncSStack;
+    /// # async fn example() -> shared_state_machine::communication::synchronizer::Result<()> {
+    /// let stack: AsyncSStack<i32> = AsyncSStack::new(7878, 1).await?;
+    /// let changes = stack.watch().top().stream();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn watch(&self) -> Watch<'_, UStack<T>, UStack<T>> {
+        self.syn.watch()
+    }
+
+    pub fn top(&self) -> Option<T> {
+        self.syn.get_lock().top()
+    }
+
+    pub fn get_lock(&self) -> std::sync::MutexGuard<'_, UStack<T>> {
+        self.syn.get_lock()
+    }
+
+    /// Nested updates resolve to a future publishing them.
+    pub fn top_mut<'a>(
+        &'a mut self,
+    ) -> UNested<T, PublishFuture<'a>, impl FnOnce(T::Update) -> PublishFuture<'a> + 'a> {
+        UNested {
+            apply_outer: move |update| -> PublishFuture<'a> {
+                Box::pin(self.syn.publish_update(UStackUpdate::Nested(update)))
+            },
+            inner_type: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use crate::communication::async_synchronizer::{AsyncSynchronizer, PublishFuture};
+use crate::communication::subscription::Watch;
+use crate::communication::synchronizer::{self, Delivery};
+use crate::ucore::unested::UNested;
+use crate::ucore::updateable;
+use crate::ucore::uvec::{UVec, UVecUpdate};
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+use updateable::Updatable;
+
+pub struct AsyncSVec<T>
+where
+    T: Updatable + Clone + Serialize,
+    <T as Updatable>::Update: Serialize,
+{
+    syn: AsyncSynchronizer<UVec<T>>,
+}
+
+impl<T> AsyncSVec<T>
+where
+    T: Updatable + Clone + Serialize + for<'de> Deserialize<'de> + Send + 'static,
+    <T as Updatable>::Update: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+{
+    async_synchronizer_methods!(UVec<T>);
+
+    pub async fn clear(&mut self) -> synchronizer::Result<Delivery> {
+        self.syn.publish_update(UVecUpdate::Clear).await
+    }
+
+    pub async fn insert(&mut self, index: usize, value: T) -> synchronizer::Result<Delivery> {
+        self.syn
+            .publish_update(UVecUpdate::Insert(index, value))
+            .await
+    }
+
+    pub async fn remove(&mut self, index: usize) -> synchronizer::Result<Delivery> {
+        self.syn.publish_update(UVecUpdate::Remove(index)).await
+    }
+
+    pub async fn push(&mut self, value: T) -> synchronizer::Result<Delivery> {
+        self.syn.publish_update(UVecUpdate::Push(value)).await
+    }
+
+    pub async fn pop(&mut self) -> synchronizer::Result<Delivery> {
+        self.syn.publish_update(UVecUpdate::Pop).await
+    }
+
+    /// Watches the changes touching a subtree, e.g. the element at an index:
+    ///
+    /// ```no_run
+    /// # use shared_state_machine::score::async_svec::AsyncSVec;
+    /// # async fn example() -> shared_state_machine::communication::synchronizer::Result<()> {
+    /// let vec: AsyncSVec<i32> = AsyncSVec::new(7878, 1).await?;
+    /// let changes = vec.watch().index(3).stream();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn watch(&self) -> Watch<'_, UVec<T>, UVec<T>> {
+        self.syn.watch()
+    }
+
+    pub fn get(&self, index: usize) -> Option<T> {
+        self.syn.get_lock().get(index)
+    }
+
+    pub fn get_lock(&self) -> std::sync::MutexGuard<'_, UVec<T>> {
+        self.syn.get_lock()
+    }
+
+    /// Nested updates resolve to a future publishing them.
+    pub fn get_mut<'a>(
+        &'a mut self,
+        index: usize,
+    ) -> UNested<T, PublishFuture<'a>, impl FnOnce(T::Update) -> PublishFuture<'a> + 'a> {
+        UNested {
+            apply_outer: move |update| -> PublishFuture<'a> {
+                Box::pin(self.syn.publish_update(UVecUpdate::Nested(index, update)))
+            },
+            inner_type: PhantomData,
+        }
+    }
+}
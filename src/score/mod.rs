@@ -1,3 +1,272 @@
+/// Methods a wrapper holding `syn: Synchronizer<$state>` forwards to it: connecting,
+/// configuration, publishing, waiting and subscriptions. `$within` is an example call
+/// of `within` on the wrapper.
+macro_rules! synchronizer_methods {
+    ($state:ty, $within:literal) => {
+        pub fn new(port: u16, group: u32) -> $crate::communication::synchronizer::Result<Self> {
+            let syn = $crate::communication::synchronizer::Synchronizer::new(port, group)?;
+            Ok(Self { syn })
+        }
+
+        pub fn with_reconnect_policy(
+            port: u16,
+            group: u32,
+            policy: $crate::communication::synchronizer::ReconnectPolicy,
+        ) -> $crate::communication::synchronizer::Result<Self> {
+            let syn = $crate::communication::synchronizer::Synchronizer::with_reconnect_policy(
+                port, group, policy,
+            )?;
+            Ok(Self { syn })
+        }
+
+        pub fn with_config(
+            config: $crate::communication::synchronizer::ClientConfig,
+            group: u32,
+        ) -> $crate::communication::synchronizer::Result<Self> {
+            let syn =
+                $crate::communication::synchronizer::Synchronizer::with_config(config, group)?;
+            Ok(Self { syn })
+        }
+
+        pub fn connection_status(&self) -> $crate::communication::synchronizer::ConnectionStatus {
+            self.syn.status()
+        }
+
+        /// Queues up to `capacity` writes while the server is unreachable instead of failing them.
+        pub fn with_offline_queue(self, capacity: usize) -> Self {
+            Self {
+                syn: self.syn.with_offline_queue(capacity),
+            }
+        }
+
+        pub fn with_retry_policy(
+            self,
+            policy: $crate::communication::synchronizer::RetryPolicy,
+        ) -> Self {
+            Self {
+                syn: self.syn.with_retry_policy(policy),
+            }
+        }
+
+        /// Limits every request to the server to `timeout`; see `Synchronizer::with_timeout`.
+        pub fn with_timeout(self, timeout: ::std::time::Duration) -> Self {
+            Self {
+                syn: self.syn.with_timeout(timeout),
+            }
+        }
+
+        #[doc = concat!("Runs `f` with requests limited to `timeout`, e.g. `", $within, "`.")]
+        pub fn within<R>(
+            &mut self,
+            timeout: ::std::time::Duration,
+            f: impl FnOnce(&mut Self) -> R,
+        ) -> R {
+            let previous = self.syn.timeout();
+            self.syn.set_timeout(Some(timeout));
+            let result = f(self);
+            self.syn.set_timeout(previous);
+            result
+        }
+
+        pub fn pending_writes(&self) -> usize {
+            self.syn.pending_writes()
+        }
+
+        pub fn take_write_reports(&self) -> Vec<$crate::communication::synchronizer::WriteReport> {
+            self.syn.take_write_reports()
+        }
+
+        /// Publishes the update `f` computes from the current state, recomputing it on conflicts.
+        pub fn update_with<F>(
+            &mut self,
+            f: F,
+        ) -> $crate::communication::synchronizer::Result<
+            Option<$crate::communication::synchronizer::Delivery>,
+        >
+        where
+            F: FnMut(&$state) -> Option<<$state as $crate::ucore::updateable::Updatable>::Update>,
+        {
+            self.syn.update_with(f)
+        }
+
+        /// Collects updates to publish atomically with `commit`.
+        pub fn transaction(&self) -> $crate::ucore::transaction::Transaction<$state> {
+            $crate::ucore::transaction::Transaction::new()
+        }
+
+        /// Publishes the updates of `transaction` as one packet, accepted or rejected as a whole.
+        pub fn commit(
+            &mut self,
+            transaction: $crate::ucore::transaction::Transaction<$state>,
+        ) -> $crate::communication::synchronizer::Result<
+            $crate::communication::synchronizer::Delivery,
+        > {
+            self.syn.publish_transaction(transaction.into_updates())
+        }
+
+        pub fn publish_snapshot(&mut self) -> $crate::communication::synchronizer::Result<()> {
+            self.syn.publish_snapshot()
+        }
+
+        /// Blocks until every update the server had accepted at call time was applied locally.
+        pub fn sync(&self) -> $crate::communication::synchronizer::Result<()> {
+            self.syn.sync()
+        }
+
+        /// Blocks for at most `timeout` until packet `packet_id` was applied locally.
+        pub fn wait_for(
+            &self,
+            packet_id: u32,
+            timeout: ::std::time::Duration,
+        ) -> $crate::communication::synchronizer::Result<()> {
+            self.syn.wait_for(packet_id, timeout)
+        }
+
+        pub fn version(&self) -> u32 {
+            self.syn.version()
+        }
+
+        /// Updates that didn't fit the local state; see `Synchronizer::take_update_failures`.
+        pub fn take_update_failures(
+            &self,
+        ) -> Vec<$crate::communication::synchronizer::UpdateFailure> {
+            self.syn.take_update_failures()
+        }
+
+        /// Subscribes to the changes applied to the local state.
+        pub fn subscribe(&self) -> $crate::communication::subscription::Subscribe<'_, $state> {
+            self.syn.subscribe()
+        }
+
+        pub fn unsubscribe(&self, id: $crate::communication::subscription::SubscriptionId) {
+            self.syn.unsubscribe(id)
+        }
+    };
+}
+
+/// Methods a wrapper holding `syn: AsyncSynchronizer<$state>` forwards to it,
+/// like `synchronizer_methods` for the blocking wrappers.
+macro_rules! async_synchronizer_methods {
+    ($state:ty) => {
+        pub async fn new(
+            port: u16,
+            group: u32,
+        ) -> $crate::communication::synchronizer::Result<Self> {
+            let syn =
+                $crate::communication::async_synchronizer::AsyncSynchronizer::new(port, group)
+                    .await?;
+            Ok(Self { syn })
+        }
+
+        pub async fn with_config(
+            config: $crate::communication::synchronizer::ClientConfig,
+            group: u32,
+        ) -> $crate::communication::synchronizer::Result<Self> {
+            let syn = $crate::communication::async_synchronizer::AsyncSynchronizer::with_config(
+                config, group,
+            )
+            .await?;
+            Ok(Self { syn })
+        }
+
+        pub fn connection_status(&self) -> $crate::communication::synchronizer::ConnectionStatus {
+            self.syn.status()
+        }
+
+        pub fn with_retry_policy(
+            self,
+            policy: $crate::communication::synchronizer::RetryPolicy,
+        ) -> Self {
+            Self {
+                syn: self.syn.with_retry_policy(policy),
+            }
+        }
+
+        /// Limits every request to the server to `timeout`; see `AsyncSynchronizer::with_timeout`.
+        pub fn with_timeout(self, timeout: ::std::time::Duration) -> Self {
+            Self {
+                syn: self.syn.with_timeout(timeout),
+            }
+        }
+
+        /// Publishes the update `f` computes from the current state, recomputing it on conflicts.
+        pub async fn update_with<F>(
+            &mut self,
+            f: F,
+        ) -> $crate::communication::synchronizer::Result<
+            Option<$crate::communication::synchronizer::Delivery>,
+        >
+        where
+            F: FnMut(&$state) -> Option<<$state as $crate::ucore::updateable::Updatable>::Update>,
+        {
+            self.syn.update_with(f).await
+        }
+
+        /// Collects updates to publish atomically with `commit`.
+        pub fn transaction(&self) -> $crate::ucore::transaction::Transaction<$state> {
+            $crate::ucore::transaction::Transaction::new()
+        }
+
+        /// Publishes the updates of `transaction` as one packet, accepted or rejected as a whole.
+        pub async fn commit(
+            &mut self,
+            transaction: $crate::ucore::transaction::Transaction<$state>,
+        ) -> $crate::communication::synchronizer::Result<
+            $crate::communication::synchronizer::Delivery,
+        > {
+            self.syn
+                .publish_transaction(transaction.into_updates())
+                .await
+        }
+
+        pub async fn publish_snapshot(
+            &mut self,
+        ) -> $crate::communication::synchronizer::Result<()> {
+            self.syn.publish_snapshot().await
+        }
+
+        /// Resolves once every update the server had accepted at call time was applied locally.
+        pub async fn sync(&mut self) -> $crate::communication::synchronizer::Result<()> {
+            self.syn.sync().await
+        }
+
+        /// Waits for at most `timeout` until packet `packet_id` was applied locally.
+        pub async fn wait_for(
+            &self,
+            packet_id: u32,
+            timeout: ::std::time::Duration,
+        ) -> $crate::communication::synchronizer::Result<()> {
+            self.syn.wait_for(packet_id, timeout).await
+        }
+
+        pub fn version(&self) -> u32 {
+            self.syn.version()
+        }
+
+        /// Updates that didn't fit the local state; see `Synchronizer::take_update_failures`.
+        pub fn take_update_failures(
+            &self,
+        ) -> Vec<$crate::communication::synchronizer::UpdateFailure> {
+            self.syn.take_update_failures()
+        }
+
+        /// Subscribes to the changes applied to the local state.
+        pub fn subscribe(&self) -> $crate::communication::subscription::Subscribe<'_, $state> {
+            self.syn.subscribe()
+        }
+
+        pub fn unsubscribe(&self, id: $crate::communication::subscription::SubscriptionId) {
+            self.syn.unsubscribe(id)
+        }
+    };
+}
+
+pub mod async_smap;
+pub mod async_sstack;
+pub mod async_svec;
+pub mod sbtreemap;
 pub mod smap;
+pub mod squeue;
+pub mod sset;
 pub mod sstack;
 pub mod svec;
@@ -0,0 +1,112 @@
+use crate::communication::subscription::Watch;
+use crate::communication::synchronizer::{self, Delivery, Synchronizer};
+use crate::ucore::ubtreemap::{owned_bounds, UBTreeMap, UBTreeMapUpdate};
+use crate::ucore::unested::UNested;
+use crate::ucore::updateable;
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+use std::ops::RangeBounds;
+use updateable::Updatable;
+
+pub struct SBTreeMap<K, T>
+where
+    K: Ord + Clone + Serialize,
+    T: Updatable + Clone + Serialize,
+    <T as Updatable>::Update: Serialize,
+{
+    syn: Synchronizer<UBTreeMap<K, T>>,
+}
+
+impl<K, T> SBTreeMap<K, T>
+where
+    K: Ord + Clone + Serialize + for<'de> Deserialize<'de> + Send + 'static,
+    T: Updatable + Clone + Serialize + for<'de> Deserialize<'de> + Send + 'static,
+    <T as Updatable>::Update: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+{
+    synchronizer_methods!(UBTreeMap<K, T>, "map.within(t, |map| map.insert(k, v))");
+
+    pub fn insert(&mut self, key: K, value: T) -> synchronizer::Result<Delivery> {
+        self.syn.publish_update(UBTreeMapUpdate::Insert(key, value))
+    }
+
+    pub fn remove(&mut self, key: K) -> synchronizer::Result<Delivery> {
+        self.syn.publish_update(UBTreeMapUpdate::Remove(key))
+    }
+
+    /// Removes the entries with keys in `range` as one update.
+    pub fn remove_range(&mut self, range: impl RangeBounds<K>) -> synchronizer::Result<Delivery> {
+        let (start, end) = owned_bounds(range);
+        self.syn
+            .publish_update(UBTreeMapUpdate::RemoveRange(start, end))
+    }
+
+    /// Watches the changes touching a subtree, e.g. the entry at a key:
+    ///
+    /// ```no_run
+    /// # use shared_state_machine::score::sbtreemap::SBTreeMap;
+    /// # fn example() -> shared_state_machine::communication::synchronizer::Result<()> {
+    /// let map: SBTreeMap<String, i32> = SBTreeMap::new(7878, 1)?;
+    /// let changes = map.watch().key(String::from("k")).channel();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn watch(&self) -> Watch<'_, UBTreeMap<K, T>, UBTreeMap<K, T>> {
+        self.syn.watch()
+    }
+
+    pub fn get(&self, key: &K) -> Option<T> {
+        self.syn.get_lock().get(key)
+    }
+
+    /// Clones of the entries with keys in `range`, in key order.
+    pub fn range(&self, range: impl RangeBounds<K>) -> Vec<(K, T)> {
+        self.syn
+            .get_lock()
+            .range(range)
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+
+    pub fn first_key_value(&self) -> Option<(K, T)> {
+        self.syn
+            .get_lock()
+            .first_key_value()
+            .map(|(key, value)| (key.clone(), value.clone()))
+    }
+
+    pub fn last_key_value(&self) -> Option<(K, T)> {
+        self.syn
+            .get_lock()
+            .last_key_value()
+            .map(|(key, value)| (key.clone(), value.clone()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.syn.get_lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.syn.get_lock().is_empty()
+    }
+
+    pub fn get_lock(&self) -> std::sync::MutexGuard<'_, UBTreeMap<K, T>> {
+        self.syn.get_lock()
+    }
+
+    pub fn get_mut(
+        &mut self,
+        key: K,
+    ) -> UNested<
+        T,
+        synchronizer::Result<Delivery>,
+        impl FnOnce(T::Update) -> synchronizer::Result<Delivery> + '_,
+    > {
+        UNested {
+            apply_outer: move |update| {
+                self.syn
+                    .publish_update(UBTreeMapUpdate::Nested(key, update))
+            },
+            inner_type: PhantomData,
+        }
+    }
+}
@@ -1,4 +1,5 @@
-use crate::communication::synchronizer::{self, Synchronizer};
+use crate::communication::subscription::Watch;
+use crate::communication::synchronizer::{self, Delivery, Synchronizer};
 use crate::ucore::umap::{UMap, UMapUpdate};
 use crate::ucore::unested::UNested;
 use crate::ucore::updateable;
@@ -22,19 +23,21 @@ where
     T: Updatable + Clone + Serialize + for<'de> Deserialize<'de> + Send + 'static,
     <T as Updatable>::Update: Serialize + for<'de> Deserialize<'de> + Send + 'static,
 {
-    pub fn new(port: u16, group: u32) -> synchronizer::Result<Self> {
-        let syn = Synchronizer::new(port, group)?;
-        Ok(SMap { syn })
-    }
+    synchronizer_methods!(UMap<K, T>, "map.within(t, |map| map.insert(k, v))");
 
-    pub fn insert(&mut self, key: K, value: T) -> synchronizer::Result<()> {
+    pub fn insert(&mut self, key: K, value: T) -> synchronizer::Result<Delivery> {
         self.syn.publish_update(UMapUpdate::Insert(key, value))
     }
 
-    pub fn remove(&mut self, key: K) -> synchronizer::Result<()> {
+    pub fn remove(&mut self, key: K) -> synchronizer::Result<Delivery> {
         self.syn.publish_update(UMapUpdate::Remove(key))
     }
 
+    /// Watches the changes touching a subtree, e.g. `watch().key(k).index(3)`.
+    pub fn watch(&self) -> Watch<'_, UMap<K, T>, UMap<K, T>> {
+        self.syn.watch()
+    }
+
     pub fn get(&self, key: &K) -> Option<T> {
         self.syn.get_lock().get(key)
     }
@@ -46,8 +49,11 @@ where
     pub fn get_mut(
         &mut self,
         key: K,
-    ) -> UNested<T, synchronizer::Result<()>, impl FnOnce(T::Update) -> synchronizer::Result<()> + '_>
-    {
+    ) -> UNested<
+        T,
+        synchronizer::Result<Delivery>,
+        impl FnOnce(T::Update) -> synchronizer::Result<Delivery> + '_,
+    > {
         UNested {
             apply_outer: move |update| self.syn.publish_update(UMapUpdate::Nested(key, update)),
             inner_type: PhantomData,
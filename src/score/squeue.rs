@@ -0,0 +1,91 @@
+use crate::communication::subscription::Watch;
+use crate::communication::synchronizer::{self, Delivery, Synchronizer};
+use crate::ucore::udeque::{UDeque, UDequeUpdate};
+use crate::ucore::unested::UNested;
+use crate::ucore::updateable;
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+use updateable::Updatable;
+
+pub struct SQueue<T>
+where
+    T: Updatable + Clone + Serialize,
+    <T as Updatable>::Update: Serialize,
+{
+    syn: Synchronizer<UDeque<T>>,
+}
+
+impl<T> SQueue<T>
+where
+    T: Updatable + Clone + Serialize + for<'de> Deserialize<'de> + Send + 'static,
+    <T as Updatable>::Update: Serialize + for<'de> Deserialize<'de> + Send + 'static,
+{
+    synchronizer_methods!(UDeque<T>, "queue.within(t, |queue| queue.push_back(v))");
+
+    pub fn push_back(&mut self, value: T) -> synchronizer::Result<Delivery> {
+        self.syn.publish_update(UDequeUpdate::PushBack(value))
+    }
+
+    pub fn push_front(&mut self, value: T) -> synchronizer::Result<Delivery> {
+        self.syn.publish_update(UDequeUpdate::PushFront(value))
+    }
+
+    pub fn pop_back(&mut self) -> synchronizer::Result<Delivery> {
+        self.syn.publish_update(UDequeUpdate::PopBack)
+    }
+
+    pub fn pop_front(&mut self) -> synchronizer::Result<Delivery> {
+        self.syn.publish_update(UDequeUpdate::PopFront)
+    }
+
+    /// Watches the changes touching a subtree, e.g. `watch().front().key(k)`.
+    pub fn watch(&self) -> Watch<'_, UDeque<T>, UDeque<T>> {
+        self.syn.watch()
+    }
+
+    pub fn front(&self) -> Option<T> {
+        self.syn.get_lock().front()
+    }
+
+    pub fn back(&self) -> Option<T> {
+        self.syn.get_lock().back()
+    }
+
+    pub fn len(&self) -> usize {
+        self.syn.get_lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.syn.get_lock().is_empty()
+    }
+
+    pub fn get_lock(&self) -> std::sync::MutexGuard<'_, UDeque<T>> {
+        self.syn.get_lock()
+    }
+
+    pub fn front_mut(
+        &mut self,
+    ) -> UNested<
+        T,
+        synchronizer::Result<Delivery>,
+        impl FnOnce(T::Update) -> synchronizer::Result<Delivery> + '_,
+    > {
+        UNested {
+            apply_outer: move |update| self.syn.publish_update(UDequeUpdate::NestedFront(update)),
+            inner_type: PhantomData,
+        }
+    }
+
+    pub fn back_mut(
+        &mut self,
+    ) -> UNested<
+        T,
+        synchronizer::Result<Delivery>,
+        impl FnOnce(T::Update) -> synchronizer::Result<Delivery> + '_,
+    > {
+        UNested {
+            apply_outer: move |update| self.syn.publish_update(UDequeUpdate::NestedBack(update)),
+            inner_type: PhantomData,
+        }
+    }
+}
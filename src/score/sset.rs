@@ -0,0 +1,64 @@
+use crate::communication::subscription::Watch;
+use crate::communication::synchronizer::{self, Delivery, Synchronizer};
+use crate::ucore::uset::{USet, USetUpdate};
+use serde::{Deserialize, Serialize};
+use std::hash::Hash;
+
+pub struct SSet<K>
+where
+    K: Eq + Hash + Clone + Serialize,
+{
+    syn: Synchronizer<USet<K>>,
+}
+
+impl<K> SSet<K>
+where
+    K: Eq + Hash + Clone + Serialize + for<'de> Deserialize<'de> + Send + 'static,
+{
+    synchronizer_methods!(USet<K>, "set.within(t, |set| set.insert(k))");
+
+    pub fn insert(&mut self, key: K) -> synchronizer::Result<Delivery> {
+        self.syn.publish_update(USetUpdate::Insert(key))
+    }
+
+    pub fn remove(&mut self, key: K) -> synchronizer::Result<Delivery> {
+        self.syn.publish_update(USetUpdate::Remove(key))
+    }
+
+    pub fn clear(&mut self) -> synchronizer::Result<Delivery> {
+        self.syn.publish_update(USetUpdate::Clear)
+    }
+
+    /// Inserts all of `keys` as one update.
+    pub fn extend(&mut self, keys: impl IntoIterator<Item = K>) -> synchronizer::Result<Delivery> {
+        self.syn
+            .publish_update(USetUpdate::Extend(keys.into_iter().collect()))
+    }
+
+    /// Watches the changes to the set, e.g. `watch().contains(k)`.
+    pub fn watch(&self) -> Watch<'_, USet<K>, USet<K>> {
+        self.syn.watch()
+    }
+
+    /// Watches the changes to whether `key` is a member.
+    pub fn watch_contains(&self, key: K) -> Watch<'_, USet<K>, bool> {
+        self.watch().contains(key)
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.syn.get_lock().contains(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.syn.get_lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.syn.get_lock().is_empty()
+    }
+
+    /// Locks the local state, e.g. to iterate over the members.
+    pub fn get_lock(&self) -> std::sync::MutexGuard<'_, USet<K>> {
+        self.syn.get_lock()
+    }
+}
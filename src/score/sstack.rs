@@ -1,4 +1,5 @@
-use crate::communication::synchronizer::{self, Synchronizer};
+use crate::communication::subscription::Watch;
+use crate::communication::synchronizer::{self, Delivery, Synchronizer};
 use crate::ucore::unested::UNested;
 use crate::ucore::updateable;
 use crate::ucore::ustack::{UStack, UStackUpdate};
@@ -19,19 +20,30 @@ where
     T: Updatable + Clone + Serialize + for<'de> Deserialize<'de> + Send + 'static,
     <T as Updatable>::Update: Serialize + for<'de> Deserialize<'de> + Send + 'static,
 {
-    pub fn new(port: u16, group: u32) -> synchronizer::Result<Self> {
-        let syn = Synchronizer::new(port, group)?;
-        Ok(SStack { syn })
-    }
+    synchronizer_methods!(UStack<T>, "stack.within(t, |stack| stack.push(v))");
 
-    pub fn push(&mut self, value: T) -> synchronizer::Result<()> {
+    pub fn push(&mut self, value: T) -> synchronizer::Result<Delivery> {
         self.syn.publish_update(UStackUpdate::Push(value))
     }
 
-    pub fn pop(&mut self) -> synchronizer::Result<()> {
+    pub fn pop(&mut self) -> synchronizer::Result<Delivery> {
         self.syn.publish_update(UStackUpdate::Pop)
     }
 
+    /// Watches the changes touching a subtree, e.g. the top of the stack:
+    ///
+    /// ```no_run
+    /// # use shared_state_machine::score::sstack::SStack;
+    /// # fn example() -> shared_state_machine::communication::synchronizer::Result<()> {
+    /// let stack: SStack<i32> = SStack::new(7878, 1)?;
+    /// let changes = stack.watch().top().channel();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn watch(&self) -> Watch<'_, UStack<T>, UStack<T>> {
+        self.syn.watch()
+    }
+
     pub fn top(&self) -> Option<T> {
         self.syn.get_lock().top()
     }
@@ -42,8 +54,11 @@ where
 
     pub fn top_mut(
         &mut self,
-    ) -> UNested<T, synchronizer::Result<()>, impl FnOnce(T::Update) -> synchronizer::Result<()> + '_>
-    {
+    ) -> UNested<
+        T,
+        synchronizer::Result<Delivery>,
+        impl FnOnce(T::Update) -> synchronizer::Result<Delivery> + '_,
+    > {
         UNested {
             apply_outer: move |update| self.syn.publish_update(UStackUpdate::Nested(update)),
             inner_type: PhantomData,
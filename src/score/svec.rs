@@ -1,4 +1,5 @@
-use crate::communication::synchronizer::{self, Synchronizer};
+use crate::communication::subscription::Watch;
+use crate::communication::synchronizer::{self, Delivery, Synchronizer};
 use crate::ucore::unested::UNested;
 use crate::ucore::updateable;
 use crate::ucore::uvec::{UVec, UVecUpdate};
@@ -19,31 +20,42 @@ where
     T: Updatable + Clone + Serialize + for<'de> Deserialize<'de> + Send + 'static,
     <T as Updatable>::Update: Serialize + for<'de> Deserialize<'de> + Send + 'static,
 {
-    pub fn new(port: u16, group: u32) -> synchronizer::Result<Self> {
-        let syn = Synchronizer::new(port, group)?;
-        Ok(SVec { syn })
-    }
+    synchronizer_methods!(UVec<T>, "vec.within(t, |vec| vec.push(v))");
 
-    pub fn clear(&mut self) -> synchronizer::Result<()> {
+    pub fn clear(&mut self) -> synchronizer::Result<Delivery> {
         self.syn.publish_update(UVecUpdate::Clear)
     }
 
-    pub fn insert(&mut self, index: usize, value: T) -> synchronizer::Result<()> {
+    pub fn insert(&mut self, index: usize, value: T) -> synchronizer::Result<Delivery> {
         self.syn.publish_update(UVecUpdate::Insert(index, value))
     }
 
-    pub fn remove(&mut self, index: usize) -> synchronizer::Result<()> {
+    pub fn remove(&mut self, index: usize) -> synchronizer::Result<Delivery> {
         self.syn.publish_update(UVecUpdate::Remove(index))
     }
 
-    pub fn push(&mut self, value: T) -> synchronizer::Result<()> {
+    pub fn push(&mut self, value: T) -> synchronizer::Result<Delivery> {
         self.syn.publish_update(UVecUpdate::Push(value))
     }
 
-    pub fn pop(&mut self) -> synchronizer::Result<()> {
+    pub fn pop(&mut self) -> synchronizer::Result<Delivery> {
         self.syn.publish_update(UVecUpdate::Pop)
     }
 
+    /// Watches the changes touching a subtree, e.g. the element at an index:
+    ///
+    /// ```no_run
+    /// # use shared_state_machine::score::svec::SVec;
+    /// # fn example() -> shared_state_machine::communication::synchronizer::Result<()> {
+    /// let vec: SVec<i32> = SVec::new(7878, 1)?;
+    /// let changes = vec.watch().index(3).channel();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn watch(&self) -> Watch<'_, UVec<T>, UVec<T>> {
+        self.syn.watch()
+    }
+
     pub fn get(&self, index: usize) -> Option<T> {
         self.syn.get_lock().get(index)
     }
@@ -55,8 +67,11 @@ where
     pub fn get_mut(
         &mut self,
         index: usize,
-    ) -> UNested<T, synchronizer::Result<()>, impl FnOnce(T::Update) -> synchronizer::Result<()> + '_>
-    {
+    ) -> UNested<
+        T,
+        synchronizer::Result<Delivery>,
+        impl FnOnce(T::Update) -> synchronizer::Result<Delivery> + '_,
+    > {
         UNested {
             apply_outer: move |update| self.syn.publish_update(UVecUpdate::Nested(index, update)),
             inner_type: PhantomData,
@@ -1,5 +1,10 @@
+pub mod numeric;
+pub mod transaction;
+pub mod ubtreemap;
+pub mod udeque;
 pub mod umap;
 pub mod unested;
 pub mod updateable;
+pub mod uset;
 pub mod ustack;
 pub mod uvec;
@@ -0,0 +1,95 @@
+use crate::ucore::unested::UNested;
+use crate::ucore::updateable;
+use serde::{Deserialize, Serialize};
+use updateable::{Updatable, UpdateError};
+
+/// Change of a number relative to its current value, so that concurrent changes compose.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum NumericUpdate<N> {
+    /// Fails with `UpdateError::Overflow` instead of wrapping around.
+    Add(N),
+    /// Fails with `UpdateError::Overflow` instead of wrapping around.
+    Sub(N),
+    SaturatingAdd(N),
+    SaturatingSub(N),
+    /// Lowers the value to at most `N`.
+    Min(N),
+    /// Raises the value to at least `N`.
+    Max(N),
+}
+
+macro_rules! impl_integer_updatable {
+    ($($t:ty),*) => {
+        $(
+            impl Updatable for $t {
+                type Update = NumericUpdate<$t>;
+                fn try_apply_update(&mut self, update: Self::Update) -> Result<(), UpdateError> {
+                    *self = match update {
+                        NumericUpdate::Add(n) => self.checked_add(n).ok_or(UpdateError::Overflow)?,
+                        NumericUpdate::Sub(n) => self.checked_sub(n).ok_or(UpdateError::Overflow)?,
+                        NumericUpdate::SaturatingAdd(n) => self.saturating_add(n),
+                        NumericUpdate::SaturatingSub(n) => self.saturating_sub(n),
+                        NumericUpdate::Min(n) => (*self).min(n),
+                        NumericUpdate::Max(n) => (*self).max(n),
+                    };
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_float_updatable {
+    ($($t:ty),*) => {
+        $(
+            /// Floats saturate to infinity on their own.
+            impl Updatable for $t {
+                type Update = NumericUpdate<$t>;
+                fn try_apply_update(&mut self, update: Self::Update) -> Result<(), UpdateError> {
+                    *self = match update {
+                        NumericUpdate::Add(n) | NumericUpdate::SaturatingAdd(n) => *self + n,
+                        NumericUpdate::Sub(n) | NumericUpdate::SaturatingSub(n) => *self - n,
+                        NumericUpdate::Min(n) => self.min(n),
+                        NumericUpdate::Max(n) => self.max(n),
+                    };
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+impl_integer_updatable!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+impl_float_updatable!(f32, f64);
+
+// Named after the operations they publish, e.g. `map.get_mut(k).add(5)`.
+#[allow(clippy::should_implement_trait)]
+impl<N, O, F> UNested<N, O, F>
+where
+    N: Updatable<Update = NumericUpdate<N>>,
+    F: FnOnce(NumericUpdate<N>) -> O,
+{
+    pub fn add(self, n: N) -> O {
+        (self.apply_outer)(NumericUpdate::Add(n))
+    }
+
+    pub fn sub(self, n: N) -> O {
+        (self.apply_outer)(NumericUpdate::Sub(n))
+    }
+
+    pub fn saturating_add(self, n: N) -> O {
+        (self.apply_outer)(NumericUpdate::SaturatingAdd(n))
+    }
+
+    pub fn saturating_sub(self, n: N) -> O {
+        (self.apply_outer)(NumericUpdate::SaturatingSub(n))
+    }
+
+    pub fn min(self, n: N) -> O {
+        (self.apply_outer)(NumericUpdate::Min(n))
+    }
+
+    pub fn max(self, n: N) -> O {
+        (self.apply_outer)(NumericUpdate::Max(n))
+    }
+}
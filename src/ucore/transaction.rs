@@ -0,0 +1,36 @@
+use crate::ucore::updateable;
+use updateable::Updatable;
+
+/// Updates collected to be published as one packet, which every replica applies atomically.
+pub struct Transaction<T: Updatable> {
+    updates: Vec<T::Update>,
+}
+
+impl<T: Updatable> Default for Transaction<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Updatable> Transaction<T> {
+    pub fn new() -> Self {
+        Transaction { updates: vec![] }
+    }
+
+    pub fn add(&mut self, update: T::Update) -> &mut Self {
+        self.updates.push(update);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.updates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.updates.is_empty()
+    }
+
+    pub fn into_updates(self) -> Vec<T::Update> {
+        self.updates
+    }
+}
@@ -0,0 +1,234 @@
+use crate::ucore::transaction::Transaction;
+use crate::ucore::unested::UNested;
+use crate::ucore::updateable;
+use serde::{Deserialize, Serialize};
+use std::collections::btree_map::{Iter, Range};
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
+use updateable::{Updatable, UpdateError};
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct UBTreeMap<K, T>
+where
+    K: Ord + Clone + Serialize,
+    T: Updatable + Clone + Serialize,
+    <T as Updatable>::Update: Serialize,
+{
+    map: BTreeMap<K, T>,
+}
+
+/// Accessor of an entry of a `UBTreeMap`, building updates of the map.
+pub type UBTreeMapEntry<K, T, F> = UNested<T, UBTreeMapUpdate<K, T>, F>;
+
+#[derive(Serialize, Deserialize)]
+pub enum UBTreeMapUpdate<K, T>
+where
+    K: Ord,
+    T: Updatable,
+{
+    Insert(K, T),
+    Remove(K),
+    /// Removes the entries with keys between the bounds; an empty or inverted range removes none.
+    RemoveRange(Bound<K>, Bound<K>),
+    Nested(K, T::Update),
+}
+
+/// Whether `BTreeMap::range` accepts the bounds instead of panicking.
+fn is_valid_range<K: Ord>(start: &Bound<K>, end: &Bound<K>) -> bool {
+    match (start, end) {
+        (Bound::Excluded(start), Bound::Excluded(end)) => start < end,
+        (
+            Bound::Included(start) | Bound::Excluded(start),
+            Bound::Included(end) | Bound::Excluded(end),
+        ) => start <= end,
+        _ => true,
+    }
+}
+
+pub(crate) fn owned_bounds<K: Clone>(range: impl RangeBounds<K>) -> (Bound<K>, Bound<K>) {
+    (range.start_bound().cloned(), range.end_bound().cloned())
+}
+
+impl<K, T> Updatable for UBTreeMap<K, T>
+where
+    K: Ord + Clone + Serialize,
+    T: Updatable + Clone + Serialize,
+    <T as Updatable>::Update: Serialize,
+{
+    type Update = UBTreeMapUpdate<K, T>;
+
+    fn try_apply_update(&mut self, update: Self::Update) -> Result<(), UpdateError> {
+        match update {
+            UBTreeMapUpdate::Insert(key, value) => {
+                self.map.insert(key, value);
+            }
+            UBTreeMapUpdate::Remove(key) => {
+                self.map.remove(&key);
+            }
+            UBTreeMapUpdate::RemoveRange(start, end) => {
+                if is_valid_range(&start, &end) {
+                    let keys: Vec<K> = self
+                        .map
+                        .range((start, end))
+                        .map(|(key, _)| key.clone())
+                        .collect();
+                    for key in keys {
+                        self.map.remove(&key);
+                    }
+                }
+            }
+            UBTreeMapUpdate::Nested(key, upd) => {
+                self.map
+                    .get_mut(&key)
+                    .ok_or(UpdateError::MissingKey)?
+                    .try_apply_update(upd)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<K, T> Default for UBTreeMap<K, T>
+where
+    K: Ord + Clone + Serialize,
+    T: Updatable + Clone + Serialize,
+    <T as Updatable>::Update: Serialize,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, T> UBTreeMap<K, T>
+where
+    K: Ord + Clone + Serialize,
+    T: Updatable + Clone + Serialize,
+    <T as Updatable>::Update: Serialize,
+{
+    pub fn new() -> Self {
+        UBTreeMap {
+            map: BTreeMap::new(),
+        }
+    }
+
+    pub fn insert(&self, key: K, value: T) -> UBTreeMapUpdate<K, T> {
+        UBTreeMapUpdate::Insert(key, value)
+    }
+
+    pub fn remove(&self, key: K) -> UBTreeMapUpdate<K, T> {
+        UBTreeMapUpdate::Remove(key)
+    }
+
+    pub fn remove_range(&self, range: impl RangeBounds<K>) -> UBTreeMapUpdate<K, T> {
+        let (start, end) = owned_bounds(range);
+        UBTreeMapUpdate::RemoveRange(start, end)
+    }
+
+    pub fn get(&self, key: &K) -> Option<T> {
+        self.map.get(key).cloned()
+    }
+
+    pub fn get_ref(&self, key: &K) -> Option<&T> {
+        self.map.get(key)
+    }
+
+    pub fn get_mut(
+        &self,
+        key: K,
+    ) -> UBTreeMapEntry<K, T, impl FnOnce(T::Update) -> UBTreeMapUpdate<K, T>> {
+        UNested {
+            apply_outer: move |update| UBTreeMapUpdate::Nested(key, update),
+            inner_type: PhantomData,
+        }
+    }
+
+    /// Entries with keys in `range`, in key order; an empty or inverted range yields none.
+    pub fn range(&self, range: impl RangeBounds<K>) -> Range<'_, K, T> {
+        let (start, end) = owned_bounds(range);
+        if !is_valid_range(&start, &end) {
+            if let Bound::Included(key) | Bound::Excluded(key) = start {
+                return self.map.range(key.clone()..key);
+            }
+        }
+        self.map.range((start, end))
+    }
+
+    pub fn first_key_value(&self) -> Option<(&K, &T)> {
+        self.map.first_key_value()
+    }
+
+    pub fn last_key_value(&self) -> Option<(&K, &T)> {
+        self.map.last_key_value()
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, T> {
+        self.map.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl<K, T, O, F> UNested<UBTreeMap<K, T>, O, F>
+where
+    K: Ord + Clone + Serialize,
+    T: Updatable + Clone + Serialize,
+    <T as Updatable>::Update: Serialize,
+    F: FnOnce(UBTreeMapUpdate<K, T>) -> O,
+{
+    pub fn insert(self, key: K, value: T) -> O {
+        (self.apply_outer)(UBTreeMapUpdate::Insert(key, value))
+    }
+
+    pub fn remove(self, key: K) -> O {
+        (self.apply_outer)(UBTreeMapUpdate::Remove(key))
+    }
+
+    pub fn remove_range(self, range: impl RangeBounds<K>) -> O {
+        let (start, end) = owned_bounds(range);
+        (self.apply_outer)(UBTreeMapUpdate::RemoveRange(start, end))
+    }
+
+    pub fn get_mut(self, key: K) -> UNested<T, O, impl FnOnce(T::Update) -> O> {
+        UNested {
+            apply_outer: move |update| (self.apply_outer)(UBTreeMapUpdate::Nested(key, update)),
+            inner_type: PhantomData,
+        }
+    }
+}
+
+impl<K, T> Transaction<UBTreeMap<K, T>>
+where
+    K: Ord + Clone + Serialize,
+    T: Updatable + Clone + Serialize,
+    <T as Updatable>::Update: Serialize,
+{
+    pub fn insert(&mut self, key: K, value: T) -> &mut Self {
+        self.add(UBTreeMapUpdate::Insert(key, value))
+    }
+
+    pub fn remove(&mut self, key: K) -> &mut Self {
+        self.add(UBTreeMapUpdate::Remove(key))
+    }
+
+    pub fn remove_range(&mut self, range: impl RangeBounds<K>) -> &mut Self {
+        let (start, end) = owned_bounds(range);
+        self.add(UBTreeMapUpdate::RemoveRange(start, end))
+    }
+
+    pub fn get_mut<'a>(
+        &'a mut self,
+        key: K,
+    ) -> UNested<T, &'a mut Self, impl FnOnce(T::Update) -> &'a mut Self + 'a> {
+        UNested {
+            apply_outer: move |update| self.add(UBTreeMapUpdate::Nested(key, update)),
+            inner_type: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use crate::ucore::transaction::Transaction;
+use crate::ucore::unested::UNested;
+use crate::ucore::updateable;
+use serde::{Deserialize, Serialize};
+use std::collections::vec_deque::Iter;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use updateable::{Updatable, UpdateError};
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub struct UDeque<T>
+where
+    T: Updatable + Clone + Serialize,
+    <T as Updatable>::Update: Serialize,
+{
+    deque: VecDeque<T>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum UDequeUpdate<T>
+where
+    T: Updatable,
+{
+    PushBack(T),
+    PushFront(T),
+    PopBack,
+    PopFront,
+    NestedFront(T::Update),
+    NestedBack(T::Update),
+}
+
+impl<T> Updatable for UDeque<T>
+where
+    T: Updatable + Clone + Serialize,
+    <T as Updatable>::Update: Serialize,
+{
+    type Update = UDequeUpdate<T>;
+
+    fn try_apply_update(&mut self, update: Self::Update) -> Result<(), UpdateError> {
+        match update {
+            UDequeUpdate::PushBack(value) => {
+                self.deque.push_back(value);
+            }
+            UDequeUpdate::PushFront(value) => {
+                self.deque.push_front(value);
+            }
+            UDequeUpdate::PopBack => {
+                self.deque.pop_back();
+            }
+            UDequeUpdate::PopFront => {
+                self.deque.pop_front();
+            }
+            UDequeUpdate::NestedFront(nested_update) => {
+                self.deque
+                    .front_mut()
+                    .ok_or(UpdateError::EmptyDeque)?
+                    .try_apply_update(nested_update)?;
+            }
+            UDequeUpdate::NestedBack(nested_update) => {
+                self.deque
+                    .back_mut()
+                    .ok_or(UpdateError::EmptyDeque)?
+                    .try_apply_update(nested_update)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T> Default for UDeque<T>
+where
+    T: Updatable + Clone + Serialize,
+    <T as Updatable>::Update: Serialize,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> UDeque<T>
+where
+    T: Updatable + Clone + Serialize,
+    <T as Updatable>::Update: Serialize,
+{
+    pub fn new() -> Self {
+        UDeque {
+            deque: VecDeque::new(),
+        }
+    }
+
+    pub fn push_back(&self, value: T) -> UDequeUpdate<T> {
+        UDequeUpdate::PushBack(value)
+    }
+
+    pub fn push_front(&self, value: T) -> UDequeUpdate<T> {
+        UDequeUpdate::PushFront(value)
+    }
+
+    pub fn pop_back(&self) -> UDequeUpdate<T> {
+        UDequeUpdate::PopBack
+    }
+
+    pub fn pop_front(&self) -> UDequeUpdate<T> {
+        UDequeUpdate::PopFront
+    }
+
+    pub fn front(&self) -> Option<T> {
+        self.deque.front().cloned()
+    }
+
+    pub fn front_ref(&self) -> Option<&T> {
+        self.deque.front()
+    }
+
+    pub fn back(&self) -> Option<T> {
+        self.deque.back().cloned()
+    }
+
+    pub fn back_ref(&self) -> Option<&T> {
+        self.deque.back()
+    }
+
+    pub fn front_mut(
+        &self,
+    ) -> UNested<T, UDequeUpdate<T>, impl FnOnce(T::Update) -> UDequeUpdate<T>> {
+        UNested {
+            apply_outer: move |update| UDequeUpdate::NestedFront(update),
+            inner_type: PhantomData,
+        }
+    }
+
+    pub fn back_mut(
+        &self,
+    ) -> UNested<T, UDequeUpdate<T>, impl FnOnce(T::Update) -> UDequeUpdate<T>> {
+        UNested {
+            apply_outer: move |update| UDequeUpdate::NestedBack(update),
+            inner_type: PhantomData,
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.deque.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.deque.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deque.is_empty()
+    }
+}
+
+impl<T, O, F> UNested<UDeque<T>, O, F>
+where
+    T: Updatable + Clone + Serialize,
+    <T as Updatable>::Update: Serialize,
+    F: FnOnce(UDequeUpdate<T>) -> O,
+{
+    pub fn push_back(self, value: T) -> O {
+        (self.apply_outer)(UDequeUpdate::PushBack(value))
+    }
+
+    pub fn push_front(self, value: T) -> O {
+        (self.apply_outer)(UDequeUpdate::PushFront(value))
+    }
+
+    pub fn pop_back(self) -> O {
+        (self.apply_outer)(UDequeUpdate::PopBack)
+    }
+
+    pub fn pop_front(self) -> O {
+        (self.apply_outer)(UDequeUpdate::PopFront)
+    }
+
+    pub fn front_mut(self) -> UNested<T, O, impl FnOnce(T::Update) -> O> {
+        UNested {
+            apply_outer: move |update| (self.apply_outer)(UDequeUpdate::NestedFront(update)),
+            inner_type: PhantomData,
+        }
+    }
+
+    pub fn back_mut(self) -> UNested<T, O, impl FnOnce(T::Update) -> O> {
+        UNested {
+            apply_outer: move |update| (self.apply_outer)(UDequeUpdate::NestedBack(update)),
+            inner_type: PhantomData,
+        }
+    }
+}
+
+impl<T> Transaction<UDeque<T>>
+where
+    T: Updatable + Clone + Serialize,
+    <T as Updatable>::Update: Serialize,
+{
+    pub fn push_back(&mut self, value: T) -> &mut Self {
+        self.add(UDequeUpdate::PushBack(value))
+    }
+
+    pub fn push_front(&mut self, value: T) -> &mut Self {
+        self.add(UDequeUpdate::PushFront(value))
+    }
+
+    pub fn pop_back(&mut self) -> &mut Self {
+        self.add(UDequeUpdate::PopBack)
+    }
+
+    pub fn pop_front(&mut self) -> &mut Self {
+        self.add(UDequeUpdate::PopFront)
+    }
+
+    pub fn front_mut<'a>(
+        &'a mut self,
+    ) -> UNested<T, &'a mut Self, impl FnOnce(T::Update) -> &'a mut Self + 'a> {
+        UNested {
+            apply_outer: move |update| self.add(UDequeUpdate::NestedFront(update)),
+            inner_type: PhantomData,
+        }
+    }
+
+    pub fn back_mut<'a>(
+        &'a mut self,
+    ) -> UNested<T, &'a mut Self, impl FnOnce(T::Update) -> &'a mut Self + 'a> {
+        UNested {
+            apply_outer: move |update| self.add(UDequeUpdate::NestedBack(update)),
+            inner_type: PhantomData,
+        }
+    }
+}
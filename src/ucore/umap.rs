@@ -1,12 +1,13 @@
+use crate::ucore::transaction::Transaction;
 use crate::ucore::unested::UNested;
 use crate::ucore::updateable;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::hash::Hash;
 use std::marker::PhantomData;
-use updateable::Updatable;
+use updateable::{Updatable, UpdateError};
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct UMap<K, T>
 where
     K: Eq + Hash + Clone + Serialize,
@@ -16,6 +17,9 @@ where
     map: HashMap<K, T>,
 }
 
+/// Accessor of an entry of a `UMap`, building updates of the map.
+pub type UMapEntry<K, T, F> = UNested<T, UMapUpdate<K, T>, F>;
+
 #[derive(Serialize, Deserialize)]
 pub enum UMapUpdate<K, T>
 where
@@ -35,7 +39,7 @@ where
 {
     type Update = UMapUpdate<K, T>;
 
-    fn apply_update(&mut self, update: Self::Update) {
+    fn try_apply_update(&mut self, update: Self::Update) -> Result<(), UpdateError> {
         match update {
             UMapUpdate::Insert(key, value) => {
                 self.map.insert(key, value);
@@ -44,13 +48,13 @@ where
                 self.map.remove(&key);
             }
             UMapUpdate::Nested(key, upd) => {
-                if !self.map.contains_key(&key) {
-                    panic!("Nested update on non-existent key!");
-                } else {
-                    self.map.get_mut(&key).unwrap().apply_update(upd);
-                }
+                self.map
+                    .get_mut(&key)
+                    .ok_or(UpdateError::MissingKey)?
+                    .try_apply_update(upd)?;
             }
         }
+        Ok(())
     }
 }
 
@@ -93,10 +97,7 @@ where
         self.map.get(key)
     }
 
-    pub fn get_mut(
-        &self,
-        key: K,
-    ) -> UNested<T, UMapUpdate<K, T>, impl FnOnce(T::Update) -> UMapUpdate<K, T>> {
+    pub fn get_mut(&self, key: K) -> UMapEntry<K, T, impl FnOnce(T::Update) -> UMapUpdate<K, T>> {
         UNested {
             apply_outer: move |update| UMapUpdate::Nested(key, update),
             inner_type: PhantomData,
@@ -126,3 +127,28 @@ where
         }
     }
 }
+
+impl<K, T> Transaction<UMap<K, T>>
+where
+    K: Eq + Hash + Clone + Serialize,
+    T: Updatable + Clone + Serialize,
+    <T as Updatable>::Update: Serialize,
+{
+    pub fn insert(&mut self, key: K, value: T) -> &mut Self {
+        self.add(UMapUpdate::Insert(key, value))
+    }
+
+    pub fn remove(&mut self, key: K) -> &mut Self {
+        self.add(UMapUpdate::Remove(key))
+    }
+
+    pub fn get_mut<'a>(
+        &'a mut self,
+        key: K,
+    ) -> UNested<T, &'a mut Self, impl FnOnce(T::Update) -> &'a mut Self + 'a> {
+        UNested {
+            apply_outer: move |update| self.add(UMapUpdate::Nested(key, update)),
+            inner_type: PhantomData,
+        }
+    }
+}
@@ -1,9 +1,35 @@
 use std::str::Bytes;
+use thiserror::Error;
+
+pub use shared_state_machine_derive::Updatable;
+
+/// Why an update couldn't be applied to a state.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum UpdateError {
+    #[error("No entry at the updated key")]
+    MissingKey,
+    #[error("Index {index} out of range for length {len}")]
+    OutOfRange { index: usize, len: usize },
+    #[error("Nested update of an empty stack")]
+    EmptyStack,
+    #[error("Nested update of an empty deque")]
+    EmptyDeque,
+    #[error("Arithmetic overflow")]
+    Overflow,
+}
 
 pub trait Updatable {
     type Update;
 
-    fn apply_update(&mut self, update: Self::Update);
+    /// Applies `update`, leaving the state unchanged if it doesn't fit.
+    fn try_apply_update(&mut self, update: Self::Update) -> Result<(), UpdateError>;
+
+    /// Applies `update`, panicking if it doesn't fit the state.
+    fn apply_update(&mut self, update: Self::Update) {
+        if let Err(error) = self.try_apply_update(update) {
+            panic!("{}", error);
+        }
+    }
 }
 
 macro_rules !impl_updatable {
@@ -11,30 +37,12 @@ macro_rules !impl_updatable {
         $(
             impl Updatable for $t {
                 type Update = ();
-                fn apply_update(&mut self, _update: Self::Update) {}
+                fn try_apply_update(&mut self, _update: Self::Update) -> Result<(), UpdateError> {
+                    Ok(())
+                }
             }
         )*
     };
 }
 
-impl_updatable!(
-    bool,
-    i8,
-    i16,
-    i32,
-    i64,
-    i128,
-    isize,
-    u8,
-    u16,
-    u32,
-    u64,
-    u128,
-    usize,
-    f32,
-    f64,
-    char,
-    str,
-    String,
-    Bytes<'_>
-);
+impl_updatable!(bool, char, str, String, Bytes<'_>);
@@ -0,0 +1,159 @@
+use crate::ucore::transaction::Transaction;
+use crate::ucore::unested::UNested;
+use crate::ucore::updateable;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_set::Iter;
+use std::collections::HashSet;
+use std::hash::Hash;
+use updateable::{Updatable, UpdateError};
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub struct USet<K>
+where
+    K: Eq + Hash + Clone + Serialize,
+{
+    set: HashSet<K>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum USetUpdate<K>
+where
+    K: Eq + Hash,
+{
+    Insert(K),
+    Remove(K),
+    Clear,
+    Extend(Vec<K>),
+}
+
+impl<K> Updatable for USet<K>
+where
+    K: Eq + Hash + Clone + Serialize,
+{
+    type Update = USetUpdate<K>;
+
+    fn try_apply_update(&mut self, update: Self::Update) -> Result<(), UpdateError> {
+        match update {
+            USetUpdate::Insert(key) => {
+                self.set.insert(key);
+            }
+            USetUpdate::Remove(key) => {
+                self.set.remove(&key);
+            }
+            USetUpdate::Clear => {
+                self.set.clear();
+            }
+            USetUpdate::Extend(keys) => {
+                self.set.extend(keys);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<K> Default for USet<K>
+where
+    K: Eq + Hash + Clone + Serialize,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K> USet<K>
+where
+    K: Eq + Hash + Clone + Serialize,
+{
+    pub fn new() -> Self {
+        USet {
+            set: HashSet::new(),
+        }
+    }
+
+    pub fn insert(&self, key: K) -> USetUpdate<K> {
+        USetUpdate::Insert(key)
+    }
+
+    pub fn remove(&self, key: K) -> USetUpdate<K> {
+        USetUpdate::Remove(key)
+    }
+
+    pub fn clear(&self) -> USetUpdate<K> {
+        USetUpdate::Clear
+    }
+
+    pub fn extend(&self, keys: impl IntoIterator<Item = K>) -> USetUpdate<K> {
+        USetUpdate::Extend(keys.into_iter().collect())
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.set.contains(key)
+    }
+
+    pub fn iter(&self) -> Iter<'_, K> {
+        self.set.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.set.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty()
+    }
+}
+
+impl<'a, K> IntoIterator for &'a USet<K>
+where
+    K: Eq + Hash + Clone + Serialize,
+{
+    type Item = &'a K;
+    type IntoIter = Iter<'a, K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.set.iter()
+    }
+}
+
+impl<K, O, F> UNested<USet<K>, O, F>
+where
+    K: Eq + Hash + Clone + Serialize,
+    F: FnOnce(USetUpdate<K>) -> O,
+{
+    pub fn insert(self, key: K) -> O {
+        (self.apply_outer)(USetUpdate::Insert(key))
+    }
+
+    pub fn remove(self, key: K) -> O {
+        (self.apply_outer)(USetUpdate::Remove(key))
+    }
+
+    pub fn clear(self) -> O {
+        (self.apply_outer)(USetUpdate::Clear)
+    }
+
+    pub fn extend(self, keys: impl IntoIterator<Item = K>) -> O {
+        (self.apply_outer)(USetUpdate::Extend(keys.into_iter().collect()))
+    }
+}
+
+impl<K> Transaction<USet<K>>
+where
+    K: Eq + Hash + Clone + Serialize,
+{
+    pub fn insert(&mut self, key: K) -> &mut Self {
+        self.add(USetUpdate::Insert(key))
+    }
+
+    pub fn remove(&mut self, key: K) -> &mut Self {
+        self.add(USetUpdate::Remove(key))
+    }
+
+    pub fn clear(&mut self) -> &mut Self {
+        self.add(USetUpdate::Clear)
+    }
+
+    pub fn extend(&mut self, keys: impl IntoIterator<Item = K>) -> &mut Self {
+        self.add(USetUpdate::Extend(keys.into_iter().collect()))
+    }
+}
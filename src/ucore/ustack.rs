@@ -1,10 +1,11 @@
+use crate::ucore::transaction::Transaction;
 use crate::ucore::unested::UNested;
 use crate::ucore::updateable;
 use serde::{Deserialize, Serialize};
 use std::marker::PhantomData;
-use updateable::Updatable;
+use updateable::{Updatable, UpdateError};
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
 pub struct UStack<T>
 where
     T: Updatable + Clone + Serialize,
@@ -30,7 +31,7 @@ where
 {
     type Update = UStackUpdate<T>;
 
-    fn apply_update(&mut self, update: Self::Update) {
+    fn try_apply_update(&mut self, update: Self::Update) -> Result<(), UpdateError> {
         match update {
             UStackUpdate::Push(value) => {
                 self.stack.push(value);
@@ -39,13 +40,13 @@ where
                 self.stack.pop();
             }
             UStackUpdate::Nested(nested_update) => {
-                if self.stack.is_empty() {
-                    panic!("Nested update on empty stack!");
-                } else {
-                    self.stack.last_mut().unwrap().apply_update(nested_update);
-                }
+                self.stack
+                    .last_mut()
+                    .ok_or(UpdateError::EmptyStack)?
+                    .try_apply_update(nested_update)?;
             }
         }
+        Ok(())
     }
 }
 
@@ -115,3 +116,26 @@ where
         }
     }
 }
+
+impl<T> Transaction<UStack<T>>
+where
+    T: Updatable + Clone + Serialize,
+    <T as Updatable>::Update: Serialize,
+{
+    pub fn push(&mut self, value: T) -> &mut Self {
+        self.add(UStackUpdate::Push(value))
+    }
+
+    pub fn pop(&mut self) -> &mut Self {
+        self.add(UStackUpdate::Pop)
+    }
+
+    pub fn top_mut<'a>(
+        &'a mut self,
+    ) -> UNested<T, &'a mut Self, impl FnOnce(T::Update) -> &'a mut Self + 'a> {
+        UNested {
+            apply_outer: move |update| self.add(UStackUpdate::Nested(update)),
+            inner_type: PhantomData,
+        }
+    }
+}
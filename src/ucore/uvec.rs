@@ -1,10 +1,11 @@
+use crate::ucore::transaction::Transaction;
 use crate::ucore::unested::UNested;
 use crate::ucore::updateable;
 use serde::{Deserialize, Serialize};
 use std::marker::PhantomData;
-use updateable::Updatable;
+use updateable::{Updatable, UpdateError};
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct UVec<T: Updatable>
 where
     T: Updatable + Clone + Serialize,
@@ -33,14 +34,21 @@ where
 {
     type Update = UVecUpdate<T>;
 
-    fn apply_update(&mut self, update: Self::Update) {
+    fn try_apply_update(&mut self, update: Self::Update) -> Result<(), UpdateError> {
+        let len = self.vec.len();
         match update {
             UVecUpdate::Clear => {
                 self.vec.clear();
             }
+            UVecUpdate::Insert(index, _) if index > len => {
+                return Err(UpdateError::OutOfRange { index, len });
+            }
             UVecUpdate::Insert(index, value) => {
                 self.vec.insert(index, value);
             }
+            UVecUpdate::Remove(index) if index >= len => {
+                return Err(UpdateError::OutOfRange { index, len });
+            }
             UVecUpdate::Remove(index) => {
                 self.vec.remove(index);
             }
@@ -51,9 +59,13 @@ where
                 self.vec.pop();
             }
             UVecUpdate::Nested(index, nested_update) => {
-                self.vec.get_mut(index).unwrap().apply_update(nested_update);
+                self.vec
+                    .get_mut(index)
+                    .ok_or(UpdateError::OutOfRange { index, len })?
+                    .try_apply_update(nested_update)?;
             }
         }
+        Ok(())
     }
 }
 
@@ -118,6 +130,10 @@ where
         self.vec.last().cloned()
     }
 
+    pub fn len(&self) -> usize {
+        self.vec.len()
+    }
+
     pub fn is_empty(&self) -> bool {
         self.vec.is_empty()
     }
@@ -156,3 +172,39 @@ where
         }
     }
 }
+
+impl<T> Transaction<UVec<T>>
+where
+    T: Updatable + Clone + Serialize,
+    <T as Updatable>::Update: Serialize,
+{
+    pub fn clear(&mut self) -> &mut Self {
+        self.add(UVecUpdate::Clear)
+    }
+
+    pub fn insert(&mut self, index: usize, value: T) -> &mut Self {
+        self.add(UVecUpdate::Insert(index, value))
+    }
+
+    pub fn remove(&mut self, index: usize) -> &mut Self {
+        self.add(UVecUpdate::Remove(index))
+    }
+
+    pub fn push(&mut self, value: T) -> &mut Self {
+        self.add(UVecUpdate::Push(value))
+    }
+
+    pub fn pop(&mut self) -> &mut Self {
+        self.add(UVecUpdate::Pop)
+    }
+
+    pub fn get_mut<'a>(
+        &'a mut self,
+        index: usize,
+    ) -> UNested<T, &'a mut Self, impl FnOnce(T::Update) -> &'a mut Self + 'a> {
+        UNested {
+            apply_outer: move |update| self.add(UVecUpdate::Nested(index, update)),
+            inner_type: PhantomData,
+        }
+    }
+}
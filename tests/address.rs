@@ -0,0 +1,65 @@
+use shared_state_machine::communication::server::{Server, ServerError};
+use shared_state_machine::communication::synchronizer::{self, ClientConfig};
+use shared_state_machine::score::async_svec::AsyncSVec;
+use shared_state_machine::score::smap::SMap;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn ipv6_address() {
+        let server = Server::bind("[::1]:0").await.unwrap();
+        let address = server.local_addr();
+        assert!(address.is_ipv6());
+
+        tokio::task::spawn_blocking(move || {
+            let status = (|| -> synchronizer::Result<()> {
+                let config = ClientConfig::new(address)?;
+                let mut map1: SMap<String, i32> = SMap::with_config(config.clone(), 1)?;
+                let map2: SMap<String, i32> = SMap::with_config(config, 1)?;
+                map1.insert(String::from("foo"), 1)?;
+                map2.sync()?;
+                assert_eq!(map2.get(&String::from("foo")), Some(1));
+                Ok(())
+            })();
+            if status.is_err() {
+                panic!("Test failed!");
+            }
+        })
+        .await
+        .unwrap();
+
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn unspecified_address() {
+        let server = Server::bind(("0.0.0.0", 0)).await.unwrap();
+        let port = server.local_addr().port();
+
+        let status = async {
+            let config = ClientConfig::new(("localhost", port))?;
+            let mut svec: AsyncSVec<i32> = AsyncSVec::with_config(config, 1).await?;
+            svec.push(3).await?;
+            assert_eq!(svec.get(0), Some(3));
+            synchronizer::Result::Ok(())
+        }
+        .await;
+        if status.is_err() {
+            panic!("Test failed!");
+        }
+
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn unresolvable_address() {
+        assert!(Server::with_address("not an address").is_err());
+        assert!(matches!(
+            Server::bind("not an address").await,
+            Err(ServerError::AddressError(_))
+        ));
+        assert!(ClientConfig::new("not an address").is_err());
+    }
+}
@@ -0,0 +1,76 @@
+use shared_state_machine::communication::server::Server;
+use shared_state_machine::communication::synchronizer::{self, Delivery};
+use shared_state_machine::score::async_smap::AsyncSMap;
+use shared_state_machine::score::async_sstack::AsyncSStack;
+use shared_state_machine::score::async_svec::AsyncSVec;
+use shared_state_machine::ucore::umap::UMap;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn async_maps_share_state() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
+
+        let status = async {
+            let mut map1: AsyncSMap<String, UMap<String, i32>> = AsyncSMap::new(port, 1).await?;
+            let mut map2: AsyncSMap<String, UMap<String, i32>> = AsyncSMap::new(port, 1).await?;
+
+            let delivery = map1.insert(String::from("foo"), UMap::new()).await?;
+            assert_eq!(delivery, Delivery::Accepted(0));
+            map2.get_mut(String::from("foo"))
+                .insert(String::from("bar"), 5)
+                .await?;
+            map1.sync().await?;
+
+            for map in [&map1, &map2] {
+                let inner = map.get(&String::from("foo")).unwrap();
+                assert_eq!(inner.get(&String::from("bar")), Some(5));
+            }
+            synchronizer::Result::Ok(())
+        }
+        .await;
+        if status.is_err() {
+            panic!("Test failed!");
+        }
+
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn async_vec_and_stack() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
+
+        let status = async {
+            let mut svec: AsyncSVec<i32> = AsyncSVec::new(port, 1).await?;
+            svec.push(1).await?;
+            svec.push(2).await?;
+            svec.insert(0, 3).await?;
+            svec.pop().await?;
+
+            let mut sstack: AsyncSStack<i32> = AsyncSStack::new(port, 2).await?;
+            sstack.push(4).await?;
+            sstack.push(5).await?;
+            sstack.pop().await?;
+
+            let mut other_svec: AsyncSVec<i32> = AsyncSVec::new(port, 1).await?;
+            let mut other_sstack: AsyncSStack<i32> = AsyncSStack::new(port, 2).await?;
+            other_svec.sync().await?;
+            other_sstack.sync().await?;
+            assert_eq!(other_svec.get(0), Some(3));
+            assert_eq!(other_svec.get(1), Some(1));
+            assert_eq!(other_svec.get(2), None);
+            assert_eq!(other_sstack.top(), Some(4));
+            synchronizer::Result::Ok(())
+        }
+        .await;
+        if status.is_err() {
+            panic!("Test failed!");
+        }
+
+        server.shutdown().await;
+    }
+}
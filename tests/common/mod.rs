@@ -0,0 +1,72 @@
+//! Helpers shared by the integration tests.
+#![allow(dead_code)]
+
+use serde_json::Value;
+use shared_state_machine::communication::messages::{ClientMessage, ServerMessage};
+use shared_state_machine::communication::server::{Server, ServerHandle};
+use shared_state_machine::communication::wal::LogConfig;
+use std::io::{Read, Write};
+use std::net::{self, ToSocketAddrs};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+use tokio_serde::formats::SymmetricalJson;
+use tokio_serde::SymmetricallyFramed;
+use tokio_util::codec::{FramedRead, FramedWrite, LengthDelimitedCodec};
+
+pub type JsonReader = SymmetricallyFramed<
+    FramedRead<OwnedReadHalf, LengthDelimitedCodec>,
+    Value,
+    SymmetricalJson<Value>,
+>;
+pub type JsonWriter = SymmetricallyFramed<
+    FramedWrite<OwnedWriteHalf, LengthDelimitedCodec>,
+    Value,
+    SymmetricalJson<Value>,
+>;
+
+/// Starts a server persisting its groups with `config`.
+pub async fn start_server<A: ToSocketAddrs>(address: A, config: LogConfig) -> ServerHandle {
+    Server::with_address(address)
+        .unwrap()
+        .with_log(config)
+        .unwrap()
+        .start()
+        .await
+        .unwrap()
+}
+
+/// Connects a raw client exchanging JSON messages with the server at `addr`.
+pub async fn connect<A: tokio::net::ToSocketAddrs>(addr: A) -> (JsonReader, JsonWriter) {
+    let (reader, writer) = TcpStream::connect(addr).await.unwrap().into_split();
+    let reader = SymmetricallyFramed::new(
+        FramedRead::new(reader, LengthDelimitedCodec::new()),
+        SymmetricalJson::default(),
+    );
+    let writer = SymmetricallyFramed::new(
+        FramedWrite::new(writer, LengthDelimitedCodec::new()),
+        SymmetricalJson::default(),
+    );
+    (reader, writer)
+}
+
+/// Sends `message` to a client, framed as the server does.
+pub fn write_message(stream: &mut net::TcpStream, message: &ServerMessage) {
+    write_frame(stream, &serde_json::to_vec(message).unwrap());
+}
+
+/// Sends `bytes` to a client as one frame.
+pub fn write_frame(stream: &mut net::TcpStream, bytes: &[u8]) {
+    stream
+        .write_all(&(bytes.len() as u32).to_be_bytes())
+        .unwrap();
+    stream.write_all(bytes).unwrap();
+}
+
+/// Receives a client's message, or `None` once the connection is closed.
+pub fn read_message(stream: &mut net::TcpStream) -> Option<ClientMessage> {
+    let mut length = [0; 4];
+    stream.read_exact(&mut length).ok()?;
+    let mut message = vec![0; u32::from_be_bytes(length) as usize];
+    stream.read_exact(&mut message).ok()?;
+    serde_json::from_slice(&message).ok()
+}
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+use shared_state_machine::communication::server::Server;
+use shared_state_machine::communication::synchronizer;
+use shared_state_machine::score::smap::SMap;
+use shared_state_machine::ucore::updateable::{Updatable, UpdateError};
+use shared_state_machine::ucore::uvec::UVec;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, Updatable)]
+enum Status {
+    Idle,
+    Playing { round: u32 },
+}
+
+#[derive(Clone, Serialize, Deserialize, Updatable)]
+struct Player {
+    name: String,
+    level: u8,
+    scores: UVec<u32>,
+    status: Status,
+}
+
+impl Player {
+    fn new(name: &str) -> Self {
+        Player {
+            name: name.to_string(),
+            level: 0,
+            scores: UVec::new(),
+            status: Status::Idle,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derived_updates() {
+        let mut player = Player::new("ada");
+        player.apply_update(player.scores_mut().push(10));
+        player.apply_update(player.level_mut().add(2));
+        player.apply_update(player.set_name(String::from("grace")));
+        player.apply_update(player.status_mut().set(Status::Playing { round: 1 }));
+        assert_eq!(player.name, "grace");
+        assert_eq!(player.level, 2);
+        assert_eq!(player.scores.get(0), Some(10));
+        assert_eq!(player.status, Status::Playing { round: 1 });
+
+        assert_eq!(
+            player.try_apply_update(player.level_mut().sub(3)),
+            Err(UpdateError::Overflow)
+        );
+        assert_eq!(player.level, 2);
+
+        let mut status = Status::Idle;
+        status.apply_update(status.set(Status::Playing { round: 2 }));
+        assert_eq!(status, Status::Playing { round: 2 });
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn derived_struct_in_shared_map() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
+
+        tokio::task::spawn_blocking(move || {
+            let status = (|| -> synchronizer::Result<()> {
+                let key = String::from("ada");
+                let mut writer: SMap<String, Player> = SMap::new(port, 1)?;
+                let reader: SMap<String, Player> = SMap::new(port, 1)?;
+                writer.insert(key.clone(), Player::new("ada"))?;
+                writer.get_mut(key.clone()).scores_mut().push(7)?;
+                writer.get_mut(key.clone()).level_mut().add(1)?;
+                writer
+                    .get_mut(key.clone())
+                    .status_mut()
+                    .set(Status::Playing { round: 3 })?;
+
+                reader.sync()?;
+                let player = reader.get(&key).unwrap();
+                assert_eq!(player.level, 1);
+                assert_eq!(player.scores.get(0), Some(7));
+                assert_eq!(player.status, Status::Playing { round: 3 });
+                Ok(())
+            })();
+            if let Err(e) = status {
+                panic!("{e}");
+            }
+        })
+        .await
+        .unwrap();
+    }
+}
@@ -0,0 +1,55 @@
+use shared_state_machine::communication::messages::ServerMessage;
+use shared_state_machine::communication::synchronizer::{self, SError};
+use shared_state_machine::score::smap::SMap;
+use std::error::Error;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread;
+
+/// Answers one client's join request with `response`.
+fn server_answering(response: ServerMessage) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buffer = [0; 1024];
+        let _ = stream.read(&mut buffer);
+        let message = serde_json::to_vec(&response).unwrap();
+        stream
+            .write_all(&(message.len() as u32).to_be_bytes())
+            .unwrap();
+        stream.write_all(&message).unwrap();
+        while stream.read(&mut buffer).is_ok_and(|read| read > 0) {}
+    });
+    port
+}
+
+fn connect(port: u16) -> synchronizer::Result<()> {
+    SMap::<i32, i32>::new(port, 1).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_are_std_errors() {
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let error = connect(port).unwrap_err();
+        assert!(matches!(error, SError::ConnectionError(_)));
+        assert!(error.to_string().starts_with("Failed to connect"));
+
+        let boxed: Box<dyn Error + Send + Sync> = error.into();
+        assert!(format!("{:?}", boxed).starts_with("ConnectionError"));
+    }
+
+    #[test]
+    fn refused_join_is_a_protocol_error() {
+        let error = connect(server_answering(ServerMessage::Head(0))).unwrap_err();
+        assert!(matches!(error, SError::ProtocolError(_)));
+    }
+}
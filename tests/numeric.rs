@@ -0,0 +1,74 @@
+use shared_state_machine::communication::server::Server;
+use shared_state_machine::communication::synchronizer;
+use shared_state_machine::score::smap::SMap;
+use shared_state_machine::ucore::numeric::NumericUpdate;
+use shared_state_machine::ucore::umap::UMap;
+use shared_state_machine::ucore::updateable::{Updatable, UpdateError};
+use std::thread;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numeric_updates() {
+        let mut umap: UMap<String, u8> = UMap::new();
+        let key = String::from("count");
+        umap.apply_update(umap.insert(key.clone(), 250));
+        umap.apply_update(umap.get_mut(key.clone()).add(3));
+        assert_eq!(umap.get(&key), Some(253));
+        assert_eq!(
+            umap.try_apply_update(umap.get_mut(key.clone()).add(3)),
+            Err(UpdateError::Overflow)
+        );
+        assert_eq!(umap.get(&key), Some(253));
+        umap.apply_update(umap.get_mut(key.clone()).saturating_add(3));
+        assert_eq!(umap.get(&key), Some(255));
+        umap.apply_update(umap.get_mut(key.clone()).min(7));
+        umap.apply_update(umap.get_mut(key.clone()).max(5));
+        assert_eq!(umap.get(&key), Some(7));
+
+        let mut value = 1.5f64;
+        value.apply_update(NumericUpdate::Sub(2.0));
+        assert_eq!(value, -0.5);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn concurrent_additions_are_not_lost() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
+
+        tokio::task::spawn_blocking(move || {
+            let status = (|| -> synchronizer::Result<()> {
+                let key = String::from("counter");
+                let mut map: SMap<String, i64> = SMap::new(port, 1)?;
+                map.insert(key.clone(), 0)?;
+                let writers: Vec<_> = (0..2)
+                    .map(|_| {
+                        let key = key.clone();
+                        thread::spawn(move || -> synchronizer::Result<()> {
+                            let mut map: SMap<String, i64> = SMap::new(port, 1)?;
+                            for _ in 0..20 {
+                                map.get_mut(key.clone()).add(2)?;
+                            }
+                            Ok(())
+                        })
+                    })
+                    .collect();
+                for writer in writers {
+                    writer.join().unwrap()?;
+                }
+                map.get_mut(key.clone()).sub(10)?;
+                assert_eq!(map.get(&key), Some(70));
+                Ok(())
+            })();
+            if let Err(error) = status {
+                panic!("Test failed: {}", error);
+            }
+        })
+        .await
+        .unwrap();
+
+        server.shutdown().await;
+    }
+}
@@ -0,0 +1,176 @@
+mod common;
+
+use common::start_server;
+use shared_state_machine::communication::messages::{ClientMessage, ServerMessage};
+use shared_state_machine::communication::synchronizer::{
+    self, ConnectionStatus, Delivery, ReconnectPolicy,
+};
+use shared_state_machine::communication::umessage::UMessage;
+use shared_state_machine::communication::wal::LogConfig;
+use shared_state_machine::score::smap::SMap;
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::{thread, time};
+
+/// Drops the first connection right after the join, and the second one right after
+/// accepting an update. Then serves the accepted update as history. Counts received updates.
+fn flaky_server(updates: Arc<AtomicUsize>) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        let mut history: Vec<UMessage> = vec![];
+        for connection in 0.. {
+            let Ok((mut stream, _)) = listener.accept() else {
+                return;
+            };
+            common::read_message(&mut stream);
+            common::write_message(&mut stream, &ServerMessage::Correct);
+            for umessage in &history {
+                common::write_message(&mut stream, &ServerMessage::Update(umessage.clone()));
+            }
+            if connection == 0 {
+                continue;
+            }
+            while let Some(message) = common::read_message(&mut stream) {
+                match message {
+                    ClientMessage::Update(umessage) => {
+                        updates.fetch_add(1, Ordering::Relaxed);
+                        let packet_id = history.len() as u32;
+                        common::write_message(&mut stream, &ServerMessage::Accepted(packet_id));
+                        history.push(umessage);
+                        if connection == 1 {
+                            break;
+                        }
+                        let umessage = history[packet_id as usize].clone();
+                        common::write_message(&mut stream, &ServerMessage::Update(umessage));
+                    }
+                    ClientMessage::Head => {
+                        let head = history.len() as u32;
+                        common::write_message(&mut stream, &ServerMessage::Head(head));
+                    }
+                    ClientMessage::JoinGroup(..) | ClientMessage::Snapshot(_) => {}
+                }
+            }
+        }
+    });
+    port
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wait_until<F: Fn() -> bool>(condition: F) {
+        for _ in 0..500 {
+            if condition() {
+                return;
+            }
+            thread::sleep(time::Duration::from_millis(10));
+        }
+        panic!("Condition never became true");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn queued_writes_are_replayed() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = LogConfig::new(dir.path());
+        let server = start_server("127.0.0.1:0", config.clone()).await;
+        let address = server.local_addr();
+        let port = address.port();
+
+        let policy = ReconnectPolicy::new().initial_backoff(time::Duration::from_millis(10));
+        let map = tokio::task::spawn_blocking(move || {
+            let status = (|| -> synchronizer::Result<SMap<String, i32>> {
+                let mut map = SMap::with_reconnect_policy(port, 1, policy)?.with_offline_queue(2);
+                let delivery = map.insert(String::from("foo"), 1)?;
+                assert!(matches!(delivery, Delivery::Accepted(0)));
+                Ok(map)
+            })();
+            match status {
+                Ok(map) => map,
+                Err(_) => panic!("Test failed!"),
+            }
+        })
+        .await
+        .unwrap();
+
+        server.shutdown().await;
+
+        let map = tokio::task::spawn_blocking(move || {
+            let status = (|| -> synchronizer::Result<SMap<String, i32>> {
+                let mut map = map;
+                wait_until(|| map.connection_status() == ConnectionStatus::Reconnecting);
+                assert_eq!(map.insert(String::from("bar"), 2)?, Delivery::Queued(0));
+                assert_eq!(map.insert(String::from("dog"), 3)?, Delivery::Queued(1));
+                // The queue is full.
+                assert!(map.insert(String::from("cat"), 4).is_err());
+                assert_eq!(map.pending_writes(), 2);
+                Ok(map)
+            })();
+            match status {
+                Ok(map) => map,
+                Err(_) => panic!("Test failed!"),
+            }
+        })
+        .await
+        .unwrap();
+
+        let server = start_server(address, config).await;
+
+        tokio::task::spawn_blocking(move || {
+            let status = (|| -> synchronizer::Result<()> {
+                let map = map;
+                wait_until(|| map.pending_writes() == 0);
+                let reports = map.take_write_reports();
+                let ids: Vec<_> = reports.iter().map(|report| report.id).collect();
+                assert_eq!(ids, vec![0, 1]);
+                assert!(reports.iter().all(|report| report.result.is_ok()));
+
+                let other: SMap<String, i32> = SMap::new(port, 1)?;
+                other.sync()?;
+                assert_eq!(other.get(&String::from("foo")), Some(1));
+                assert_eq!(other.get(&String::from("bar")), Some(2));
+                assert_eq!(other.get(&String::from("dog")), Some(3));
+                assert_eq!(other.get(&String::from("cat")), None);
+                Ok(())
+            })();
+            if status.is_err() {
+                panic!("Test failed!");
+            }
+        })
+        .await
+        .unwrap();
+
+        server.shutdown().await;
+    }
+
+    #[test]
+    fn accepted_writes_are_not_replayed_again() {
+        let updates = Arc::new(AtomicUsize::new(0));
+        let port = flaky_server(updates.clone());
+        let status = (|| -> synchronizer::Result<()> {
+            let policy = ReconnectPolicy::new().initial_backoff(time::Duration::from_millis(200));
+            let mut map: SMap<String, i32> =
+                SMap::with_reconnect_policy(port, 1, policy)?.with_offline_queue(1);
+            wait_until(|| map.connection_status() == ConnectionStatus::Reconnecting);
+            let delivery = map.insert(String::from("foo"), 1)?;
+            assert!(matches!(delivery, Delivery::Queued(0)));
+
+            // The connection is lost after the server accepted the replayed write.
+            wait_until(|| map.pending_writes() == 0);
+            let reports = map.take_write_reports();
+            assert_eq!(reports.len(), 1);
+            assert!(matches!(reports[0].result, Ok(0)));
+
+            wait_until(|| map.connection_status() == ConnectionStatus::Connected);
+            map.sync()?;
+            assert_eq!(map.get(&String::from("foo")), Some(1));
+            assert_eq!(updates.load(Ordering::Relaxed), 1);
+            Ok(())
+        })();
+        if let Err(error) = status {
+            panic!("Test failed: {}", error);
+        }
+    }
+}
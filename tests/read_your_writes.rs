@@ -0,0 +1,54 @@
+use shared_state_machine::communication::server::Server;
+use shared_state_machine::communication::synchronizer;
+use shared_state_machine::score::async_svec::AsyncSVec;
+use shared_state_machine::score::smap::SMap;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn writes_are_visible_on_return() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
+
+        tokio::task::spawn_blocking(move || {
+            let status = (|| -> synchronizer::Result<()> {
+                let mut map: SMap<String, i32> = SMap::new(port, 1)?;
+                for i in 0..50 {
+                    map.insert(String::from("foo"), i)?;
+                    assert_eq!(map.get(&String::from("foo")), Some(i));
+                }
+                Ok(())
+            })();
+            if status.is_err() {
+                panic!("Test failed!");
+            }
+        })
+        .await
+        .unwrap();
+
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn async_writes_are_visible_on_return() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
+
+        let status = async {
+            let mut svec: AsyncSVec<i32> = AsyncSVec::new(port, 1).await?;
+            for i in 0..50 {
+                svec.push(i).await?;
+                assert_eq!(svec.get(i as usize), Some(i));
+            }
+            synchronizer::Result::Ok(())
+        }
+        .await;
+        if status.is_err() {
+            panic!("Test failed!");
+        }
+
+        server.shutdown().await;
+    }
+}
@@ -0,0 +1,252 @@
+mod common;
+
+use common::start_server;
+use shared_state_machine::communication::messages::ServerMessage;
+use shared_state_machine::communication::synchronizer::{
+    self, ClientConfig, ConnectionStatus, ReconnectPolicy, SError,
+};
+use shared_state_machine::communication::wal::LogConfig;
+use shared_state_machine::score::async_smap::AsyncSMap;
+use shared_state_machine::score::smap::SMap;
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::{thread, time};
+
+/// Accepts clients' join requests, then answers their next request with a frame
+/// that isn't a message. Counts the connections in `connections`.
+fn garbling_server(connections: Arc<AtomicUsize>) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else {
+                return;
+            };
+            connections.fetch_add(1, Ordering::SeqCst);
+            thread::spawn(move || {
+                common::read_message(&mut stream);
+                common::write_message(&mut stream, &ServerMessage::Correct);
+                if common::read_message(&mut stream).is_some() {
+                    common::write_frame(&mut stream, b"not a message");
+                }
+                while common::read_message(&mut stream).is_some() {}
+            });
+        }
+    });
+    port
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wait_for_status(map: &SMap<String, i32>, status: ConnectionStatus) {
+        for _ in 0..500 {
+            if map.connection_status() == status {
+                return;
+            }
+            thread::sleep(time::Duration::from_millis(10));
+        }
+        panic!("Connection status never reached {:?}", status);
+    }
+
+    async fn wait_for_async_status(map: &AsyncSMap<String, i32>, status: ConnectionStatus) {
+        for _ in 0..500 {
+            if map.connection_status() == status {
+                return;
+            }
+            tokio::time::sleep(time::Duration::from_millis(10)).await;
+        }
+        panic!("Connection status never reached {:?}", status);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn resumes_after_server_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = LogConfig::new(dir.path());
+        let server = start_server("127.0.0.1:0", config.clone()).await;
+        let address = server.local_addr();
+        let port = address.port();
+
+        let policy = ReconnectPolicy::new().initial_backoff(time::Duration::from_millis(10));
+        let map = tokio::task::spawn_blocking(move || {
+            let status = (|| -> synchronizer::Result<SMap<String, i32>> {
+                let mut map = SMap::with_reconnect_policy(port, 1, policy)?;
+                map.insert(String::from("foo"), 1)?;
+                Ok(map)
+            })();
+            match status {
+                Ok(map) => map,
+                Err(_) => panic!("Test failed!"),
+            }
+        })
+        .await
+        .unwrap();
+
+        server.shutdown().await;
+
+        let map = tokio::task::spawn_blocking(move || {
+            let mut map = map;
+            wait_for_status(&map, ConnectionStatus::Reconnecting);
+            // Writes fail while the server is unreachable, local state is kept.
+            assert!(map.insert(String::from("bar"), 2).is_err());
+            assert_eq!(map.get(&String::from("foo")), Some(1));
+            map
+        })
+        .await
+        .unwrap();
+
+        let server = start_server(address, config).await;
+
+        tokio::task::spawn_blocking(move || {
+            let status = (|| -> synchronizer::Result<()> {
+                let mut map = map;
+                wait_for_status(&map, ConnectionStatus::Connected);
+                map.insert(String::from("bar"), 2)?;
+                assert_eq!(map.get(&String::from("foo")), Some(1));
+
+                let other: SMap<String, i32> = SMap::new(port, 1)?;
+                other.sync()?;
+                assert_eq!(other.get(&String::from("foo")), Some(1));
+                assert_eq!(other.get(&String::from("bar")), Some(2));
+                Ok(())
+            })();
+            if status.is_err() {
+                panic!("Test failed!");
+            }
+        })
+        .await
+        .unwrap();
+
+        server.shutdown().await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn gives_up_after_max_attempts() {
+        let dir = tempfile::tempdir().unwrap();
+        let server = start_server("127.0.0.1:0", LogConfig::new(dir.path())).await;
+        let address = server.local_addr();
+        let port = address.port();
+
+        let policy = ReconnectPolicy::new()
+            .initial_backoff(time::Duration::from_millis(10))
+            .max_attempts(2);
+        let map = tokio::task::spawn_blocking(move || {
+            match SMap::<String, i32>::with_reconnect_policy(port, 1, policy) {
+                Ok(map) => map,
+                Err(_) => panic!("Test failed!"),
+            }
+        })
+        .await
+        .unwrap();
+
+        server.shutdown().await;
+
+        tokio::task::spawn_blocking(move || {
+            let mut map = map;
+            wait_for_status(&map, ConnectionStatus::Failed);
+            assert!(map.insert(String::from("foo"), 1).is_err());
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn async_client_resumes_after_server_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_config = LogConfig::new(dir.path());
+        let server = start_server("127.0.0.1:0", log_config.clone()).await;
+        let address = server.local_addr();
+        let port = address.port();
+
+        let policy = ReconnectPolicy::new().initial_backoff(time::Duration::from_millis(10));
+        let config = ClientConfig::local(port).reconnect_policy(policy);
+        let Ok(mut map) = AsyncSMap::<String, i32>::with_config(config, 1).await else {
+            panic!("Test failed!");
+        };
+        assert!(map.insert(String::from("foo"), 1).await.is_ok());
+
+        server.shutdown().await;
+        wait_for_async_status(&map, ConnectionStatus::Reconnecting).await;
+        // Writes fail while the server is unreachable, local state is kept.
+        assert!(map.insert(String::from("bar"), 2).await.is_err());
+        assert_eq!(map.get(&String::from("foo")), Some(1));
+
+        let server = start_server(address, log_config).await;
+        wait_for_async_status(&map, ConnectionStatus::Connected).await;
+        let status = async {
+            map.insert(String::from("bar"), 2).await?;
+            assert_eq!(map.get(&String::from("foo")), Some(1));
+
+            let mut other: AsyncSMap<String, i32> = AsyncSMap::new(port, 1).await?;
+            other.sync().await?;
+            assert_eq!(other.get(&String::from("foo")), Some(1));
+            assert_eq!(other.get(&String::from("bar")), Some(2));
+            synchronizer::Result::Ok(())
+        }
+        .await;
+        if status.is_err() {
+            panic!("Test failed!");
+        }
+
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn async_client_gives_up_after_max_attempts() {
+        let dir = tempfile::tempdir().unwrap();
+        let server = start_server("127.0.0.1:0", LogConfig::new(dir.path())).await;
+        let port = server.local_addr().port();
+
+        let policy = ReconnectPolicy::new()
+            .initial_backoff(time::Duration::from_millis(10))
+            .max_attempts(2);
+        let config = ClientConfig::local(port).reconnect_policy(policy);
+        let Ok(mut map) = AsyncSMap::<String, i32>::with_config(config, 1).await else {
+            panic!("Test failed!");
+        };
+
+        server.shutdown().await;
+        wait_for_async_status(&map, ConnectionStatus::Failed).await;
+        assert!(map.insert(String::from("foo"), 1).await.is_err());
+    }
+
+    #[test]
+    fn malformed_messages_are_reported_without_reconnecting() {
+        let connections = Arc::new(AtomicUsize::new(0));
+        let port = garbling_server(connections.clone());
+        let policy = ReconnectPolicy::new().initial_backoff(time::Duration::from_millis(10));
+        let Ok(mut map) = SMap::<String, i32>::with_reconnect_policy(port, 1, policy) else {
+            panic!("Test failed!");
+        };
+        assert!(matches!(
+            map.insert(String::from("foo"), 1),
+            Err(SError::SerializationError(_))
+        ));
+        assert_eq!(map.connection_status(), ConnectionStatus::Failed);
+        assert!(matches!(map.sync(), Err(SError::SerializationError(_))));
+        thread::sleep(time::Duration::from_millis(100));
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn async_malformed_messages_are_reported_without_reconnecting() {
+        let connections = Arc::new(AtomicUsize::new(0));
+        let port = garbling_server(connections.clone());
+        let Ok(mut map) = AsyncSMap::<String, i32>::new(port, 1).await else {
+            panic!("Test failed!");
+        };
+        assert!(matches!(
+            map.insert(String::from("foo"), 1).await,
+            Err(SError::SerializationError(_))
+        ));
+        assert!(matches!(
+            map.sync().await,
+            Err(SError::SerializationError(_))
+        ));
+        assert_eq!(map.connection_status(), ConnectionStatus::Failed);
+        tokio::time::sleep(time::Duration::from_millis(100)).await;
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,59 @@
+use shared_state_machine::communication::server::Server;
+use shared_state_machine::communication::subscription::Change;
+use shared_state_machine::communication::synchronizer;
+use shared_state_machine::score::sbtreemap::SBTreeMap;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn shared_schedule() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
+
+        tokio::task::spawn_blocking(move || {
+            let status = (|| -> synchronizer::Result<()> {
+                let mut planner: SBTreeMap<u64, String> = SBTreeMap::new(port, 1)?;
+                let mut runner: SBTreeMap<u64, String> = SBTreeMap::new(port, 1)?;
+                let changes = runner.watch().key(900).channel();
+
+                planner.insert(1200, String::from("lunch"))?; // 0
+                planner.insert(900, String::from("standup"))?; // 1
+                planner.insert(1700, String::from("retro"))?; // 2
+                runner.sync()?;
+                assert_eq!(
+                    runner.first_key_value(),
+                    Some((900, String::from("standup")))
+                );
+                assert_eq!(runner.last_key_value(), Some((1700, String::from("retro"))));
+                assert_eq!(
+                    runner.range(1000..),
+                    vec![(1200, String::from("lunch")), (1700, String::from("retro"))]
+                );
+
+                runner.remove_range(..=1200)?; // 3
+                planner.sync()?;
+                assert_eq!(planner.len(), 1);
+                assert_eq!(planner.first_key_value().map(|(key, _)| key), Some(1700));
+
+                let packet_ids: Vec<_> = changes
+                    .try_iter()
+                    .filter_map(|change| match change {
+                        Change::Update { packet_id, .. } => Some(packet_id),
+                        Change::Replaced { .. } => None,
+                    })
+                    .collect();
+                assert_eq!(packet_ids, vec![1, 3]);
+                Ok(())
+            })();
+            if let Err(e) = status {
+                panic!("{e}");
+            }
+        })
+        .await
+        .unwrap();
+
+        server.shutdown().await;
+    }
+}
@@ -1,13 +1,15 @@
+mod common;
+
 use futures::prelude::*;
-use serde_json::{json, Value};
-use shared_state_machine::communication::messages::{ClientMessage, ServerMessage};
-use shared_state_machine::communication::server::Server;
-use shared_state_machine::communication::umessage::UMessage;
+use serde_json::json;
+use shared_state_machine::communication::messages::{
+    ClientMessage, Rejection, RejectionReason, ServerMessage,
+};
+use shared_state_machine::communication::server::{Server, ServerError};
+use shared_state_machine::communication::umessage::{UMessage, USnapshot};
+use shared_state_machine::ucore::updateable::Updatable;
 use shared_state_machine::ucore::ustack::UStack;
 use tokio::net::TcpStream;
-use tokio_serde::formats::*;
-use tokio_util::codec::{FramedRead, FramedWrite, LengthDelimitedCodec};
-use tokio_util::sync::CancellationToken;
 
 #[cfg(test)]
 mod tests {
@@ -24,78 +26,23 @@ mod tests {
 
         //-- (1) --//
 
-        let shutdown_token = CancellationToken::new();
-        let server_shutdown_token = shutdown_token.clone();
-
-        let server_handle = tokio::spawn(async move {
-            let server = Server::new(7878);
-            server.run(server_shutdown_token).await;
-        });
-        tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
 
         //-- (2) --//
-        let addr = "127.0.0.1:7878";
+        let addr = server.local_addr();
 
         // Setup clients' readers and writers.
-        let client1 = TcpStream::connect(addr).await.unwrap();
-        let (reader, writer) = client1.into_split();
-        let mut reader1 = {
-            let length_delimited = FramedRead::new(reader, LengthDelimitedCodec::new());
-            tokio_serde::SymmetricallyFramed::new(
-                length_delimited,
-                SymmetricalJson::<Value>::default(),
-            )
-        };
-        let mut writer1 = {
-            let length_delimited = FramedWrite::new(writer, LengthDelimitedCodec::new());
-            tokio_serde::SymmetricallyFramed::new(length_delimited, SymmetricalJson::default())
-        };
+        let (mut reader1, mut writer1) = common::connect(addr).await;
 
-        let client2 = TcpStream::connect(addr).await.unwrap();
-        let (reader, writer) = client2.into_split();
-        let mut reader2 = {
-            let length_delimited = FramedRead::new(reader, LengthDelimitedCodec::new());
-            tokio_serde::SymmetricallyFramed::new(
-                length_delimited,
-                SymmetricalJson::<Value>::default(),
-            )
-        };
-        let mut writer2 = {
-            let length_delimited = FramedWrite::new(writer, LengthDelimitedCodec::new());
-            tokio_serde::SymmetricallyFramed::new(length_delimited, SymmetricalJson::default())
-        };
+        let (mut reader2, mut writer2) = common::connect(addr).await;
 
-        let client3 = TcpStream::connect(addr).await.unwrap();
-        let (reader, writer) = client3.into_split();
-        let mut reader3 = {
-            let length_delimited = FramedRead::new(reader, LengthDelimitedCodec::new());
-            tokio_serde::SymmetricallyFramed::new(
-                length_delimited,
-                SymmetricalJson::<Value>::default(),
-            )
-        };
-        let mut writer3 = {
-            let length_delimited = FramedWrite::new(writer, LengthDelimitedCodec::new());
-            tokio_serde::SymmetricallyFramed::new(length_delimited, SymmetricalJson::default())
-        };
+        let (mut reader3, mut writer3) = common::connect(addr).await;
 
-        let client4 = TcpStream::connect(addr).await.unwrap();
-        let (reader, writer) = client4.into_split();
-        let mut reader4 = {
-            let length_delimited = FramedRead::new(reader, LengthDelimitedCodec::new());
-            tokio_serde::SymmetricallyFramed::new(
-                length_delimited,
-                SymmetricalJson::<Value>::default(),
-            )
-        };
-        let mut writer4 = {
-            let length_delimited = FramedWrite::new(writer, LengthDelimitedCodec::new());
-            tokio_serde::SymmetricallyFramed::new(length_delimited, SymmetricalJson::default())
-        };
+        let (mut reader4, mut writer4) = common::connect(addr).await;
         // END SETUP
 
-        let join1 = ClientMessage::JoinGroup(1);
-        let join2 = ClientMessage::JoinGroup(2);
+        let join1 = ClientMessage::JoinGroup(1, None);
+        let join2 = ClientMessage::JoinGroup(2, None);
 
         // Client 1 joins group 1.
         writer1.send(json!(join1)).await.unwrap();
@@ -115,7 +62,7 @@ mod tests {
 
         writer1.send(json!(update1)).await.unwrap();
         let msg = reader1.try_next().await.unwrap().unwrap();
-        assert_eq!(msg, json!(ServerMessage::Correct));
+        assert_eq!(msg, json!(ServerMessage::Accepted(0)));
         //-- (3) --//
 
         // Client 1 should receive update.
@@ -134,7 +81,7 @@ mod tests {
         // Client 2 sends and update that should be broadcasted to client 1.
         writer2.send(json!(update2)).await.unwrap();
         let msg = reader2.try_next().await.unwrap().unwrap();
-        assert_eq!(msg, json!(ServerMessage::Correct));
+        assert_eq!(msg, json!(ServerMessage::Accepted(1)));
 
         // Client 1 should receive update.
         let msg = reader1.try_next().await.unwrap().unwrap();
@@ -160,17 +107,283 @@ mod tests {
         let update3 = ClientMessage::Update(UMessage::new(1, 0, &push_5).unwrap());
         writer3.send(json!(update3)).await.unwrap();
         let msg = reader3.try_next().await.unwrap().unwrap();
-        assert_eq!(msg, json!(ServerMessage::Correct));
+        assert_eq!(msg, json!(ServerMessage::Accepted(0)));
         let msg = reader3.try_next().await.unwrap().unwrap();
         assert_eq!(msg, json!(update3));
 
         let update4 = ClientMessage::Update(UMessage::new(1, 0, &push_5).unwrap());
         writer3.send(json!(update4)).await.unwrap();
         let msg = reader3.try_next().await.unwrap().unwrap();
-        assert_eq!(msg, json!(ServerMessage::Error));
+        assert_eq!(
+            msg,
+            json!(ServerMessage::Rejected(Rejection {
+                packet_id: 0,
+                head: 1,
+                reason: RejectionReason::StalePacketId,
+            }))
+        );
 
         // Graceful shutdown of server.
-        shutdown_token.cancel();
-        server_handle.await.unwrap();
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn snapshot_replaces_compacted_history() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+
+        let addr = server.local_addr();
+
+        let (mut reader1, mut writer1) = common::connect(addr).await;
+
+        let (mut reader2, mut writer2) = common::connect(addr).await;
+
+        writer1
+            .send(json!(ClientMessage::JoinGroup(1, None)))
+            .await
+            .unwrap();
+        let msg = reader1.try_next().await.unwrap().unwrap();
+        assert_eq!(msg, json!(ServerMessage::Correct));
+
+        // Client 1 pushes three values and keeps its own replica up to date.
+        let mut ustack: UStack<i32> = UStack::new();
+        let mut updates = vec![];
+        for packet_id in 0..3 {
+            let push = ustack.push(packet_id as i32);
+            let update = ClientMessage::Update(UMessage::new(1, packet_id, &push).unwrap());
+            writer1.send(json!(update)).await.unwrap();
+            let msg = reader1.try_next().await.unwrap().unwrap();
+            assert_eq!(msg, json!(ServerMessage::Accepted(packet_id)));
+            let msg = reader1.try_next().await.unwrap().unwrap();
+            assert_eq!(msg, json!(update));
+            if packet_id < 2 {
+                ustack.apply_update(push);
+            }
+            updates.push(update);
+        }
+
+        // A snapshot can't claim updates the server hasn't accepted yet.
+        let snapshot = USnapshot::new(1, 5, &ustack).unwrap();
+        writer1
+            .send(json!(ClientMessage::Snapshot(snapshot)))
+            .await
+            .unwrap();
+        let msg = reader1.try_next().await.unwrap().unwrap();
+        assert_eq!(
+            msg,
+            json!(ServerMessage::Rejected(Rejection {
+                packet_id: 5,
+                head: 3,
+                reason: RejectionReason::InvalidSnapshot,
+            }))
+        );
+
+        // Snapshot of the state after the first two updates.
+        let snapshot = USnapshot::new(1, 2, &ustack).unwrap();
+        writer1
+            .send(json!(ClientMessage::Snapshot(snapshot.clone())))
+            .await
+            .unwrap();
+        let msg = reader1.try_next().await.unwrap().unwrap();
+        assert_eq!(msg, json!(ServerMessage::Accepted(2)));
+
+        // Client 2 receives the snapshot followed by the remaining tail.
+        writer2
+            .send(json!(ClientMessage::JoinGroup(1, None)))
+            .await
+            .unwrap();
+        let msg = reader2.try_next().await.unwrap().unwrap();
+        assert_eq!(msg, json!(ServerMessage::Correct));
+        let msg = reader2.try_next().await.unwrap().unwrap();
+        assert_eq!(msg, json!(ServerMessage::Snapshot(snapshot)));
+        let msg = reader2.try_next().await.unwrap().unwrap();
+        assert_eq!(msg, json!(updates[2]));
+
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn resume_from_offset() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+
+        let addr = server.local_addr();
+
+        let (mut reader1, mut writer1) = common::connect(addr).await;
+
+        let (mut reader2, mut writer2) = common::connect(addr).await;
+
+        let (mut reader3, mut writer3) = common::connect(addr).await;
+
+        writer1
+            .send(json!(ClientMessage::JoinGroup(1, None)))
+            .await
+            .unwrap();
+        let msg = reader1.try_next().await.unwrap().unwrap();
+        assert_eq!(msg, json!(ServerMessage::Correct));
+
+        let ustack: UStack<i32> = UStack::new();
+        let mut updates = vec![];
+        for packet_id in 0..3 {
+            let push = ustack.push(packet_id as i32);
+            let update = ClientMessage::Update(UMessage::new(1, packet_id, &push).unwrap());
+            writer1.send(json!(update)).await.unwrap();
+            let msg = reader1.try_next().await.unwrap().unwrap();
+            assert_eq!(msg, json!(ServerMessage::Accepted(packet_id)));
+            let msg = reader1.try_next().await.unwrap().unwrap();
+            assert_eq!(msg, json!(update));
+            updates.push(update);
+        }
+
+        // Client 2 already holds the first two updates and only receives the last one.
+        writer2
+            .send(json!(ClientMessage::JoinGroup(1, Some(2))))
+            .await
+            .unwrap();
+        let msg = reader2.try_next().await.unwrap().unwrap();
+        assert_eq!(msg, json!(ServerMessage::Correct));
+        let msg = reader2.try_next().await.unwrap().unwrap();
+        assert_eq!(msg, json!(updates[2]));
+
+        // Client 3 claims updates the server has never seen and is sent the full history.
+        writer3
+            .send(json!(ClientMessage::JoinGroup(1, Some(7))))
+            .await
+            .unwrap();
+        let msg = reader3.try_next().await.unwrap().unwrap();
+        assert_eq!(msg, json!(ServerMessage::OffsetUnavailable));
+        for update in &updates {
+            let msg = reader3.try_next().await.unwrap().unwrap();
+            assert_eq!(msg, json!(update));
+        }
+
+        // New updates are broadcast to resumed clients as usual.
+        let update = ClientMessage::Update(UMessage::new(1, 3, &ustack.pop()).unwrap());
+        writer1.send(json!(update)).await.unwrap();
+        let msg = reader1.try_next().await.unwrap().unwrap();
+        assert_eq!(msg, json!(ServerMessage::Accepted(3)));
+        let msg = reader2.try_next().await.unwrap().unwrap();
+        assert_eq!(msg, json!(update));
+        let msg = reader3.try_next().await.unwrap().unwrap();
+        assert_eq!(msg, json!(update));
+
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn handle_reports_address_and_shuts_down() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr();
+        assert_ne!(addr.port(), 0);
+
+        // The address is taken.
+        assert!(matches!(
+            Server::bind(addr).await,
+            Err(ServerError::BindError(_))
+        ));
+
+        let (mut reader, mut writer) = common::connect(addr).await;
+        writer
+            .send(json!(ClientMessage::JoinGroup(1, None)))
+            .await
+            .unwrap();
+        let msg = reader.try_next().await.unwrap().unwrap();
+        assert_eq!(msg, json!(ServerMessage::Correct));
+
+        // Every connection is closed once shutdown returns.
+        server.shutdown().await;
+        assert!(matches!(reader.try_next().await, Ok(None) | Err(_)));
+        assert!(TcpStream::connect(addr).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn lagging_client_catches_up() {
+        let server = Server::with_address("127.0.0.1:0")
+            .unwrap()
+            .with_channel_capacity(1)
+            .unwrap()
+            .start()
+            .await
+            .unwrap();
+        let addr = server.local_addr();
+
+        let mut clients = vec![];
+        for _ in 0..2 {
+            let (mut reader, mut writer) = common::connect(addr).await;
+            writer
+                .send(json!(ClientMessage::JoinGroup(1, None)))
+                .await
+                .unwrap();
+            let msg = reader.try_next().await.unwrap().unwrap();
+            assert_eq!(msg, json!(ServerMessage::Correct));
+            clients.push((reader, writer));
+        }
+        let (mut slow_reader, _slow_writer) = clients.remove(0);
+        let (mut reader, mut writer) = clients.remove(0);
+
+        // Large updates fill the socket buffers of the client that doesn't read,
+        // so its connection falls behind the broadcast channel.
+        let ustack: UStack<String> = UStack::new();
+        let updates = 40;
+        for packet_id in 0..updates {
+            let push = ustack.push("x".repeat(128 * 1024));
+            let update = ClientMessage::Update(UMessage::new(1, packet_id, &push).unwrap());
+            writer.send(json!(update)).await.unwrap();
+            loop {
+                let msg = reader.try_next().await.unwrap().unwrap();
+                if msg == json!(ServerMessage::Accepted(packet_id)) {
+                    break;
+                }
+            }
+        }
+
+        for packet_id in 0..updates {
+            let msg = slow_reader.try_next().await.unwrap().unwrap();
+            match serde_json::from_value::<ServerMessage>(msg).unwrap() {
+                ServerMessage::Update(umessage) => assert_eq!(umessage.packet_id, packet_id),
+                _ => panic!("Expected an update"),
+            }
+        }
+
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn malformed_messages_are_rejected() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let (mut reader, mut writer) = common::connect(server.local_addr()).await;
+        writer
+            .send(json!(ClientMessage::JoinGroup(1, None)))
+            .await
+            .unwrap();
+        let msg = reader.try_next().await.unwrap().unwrap();
+        assert_eq!(msg, json!(ServerMessage::Correct));
+
+        writer.send(json!({ "Bogus": 1 })).await.unwrap();
+        let msg = reader.try_next().await.unwrap().unwrap();
+        let expected = |packet_id| {
+            json!(ServerMessage::Rejected(Rejection {
+                packet_id,
+                head: 0,
+                reason: RejectionReason::MalformedPayload,
+            }))
+        };
+        assert_eq!(msg, expected(0));
+
+        let mut umessage = UMessage::new(1, 0, &()).unwrap();
+        umessage.update = String::from("{ not json");
+        writer
+            .send(json!(ClientMessage::Update(umessage)))
+            .await
+            .unwrap();
+        let msg = reader.try_next().await.unwrap().unwrap();
+        assert_eq!(msg, expected(0));
+
+        // The connection stays usable.
+        let ustack: UStack<i32> = UStack::new();
+        let update = ClientMessage::Update(UMessage::new(1, 0, &ustack.push(1)).unwrap());
+        writer.send(json!(update)).await.unwrap();
+        let msg = reader.try_next().await.unwrap().unwrap();
+        assert_eq!(msg, json!(ServerMessage::Accepted(0)));
+
+        server.shutdown().await;
     }
 }
@@ -2,7 +2,6 @@ use shared_state_machine::communication::synchronizer;
 use shared_state_machine::score::smap::SMap;
 use shared_state_machine::{communication::server::Server, ucore::umap::UMap};
 use std::{thread, time};
-use tokio_util::sync::CancellationToken;
 
 #[cfg(test)]
 mod tests {
@@ -11,21 +10,13 @@ mod tests {
 
     #[tokio::test]
     async fn two_clients() {
-        let shutdown_token = CancellationToken::new();
-        let server_shutdown_token = shutdown_token.clone();
-
-        let port = 7870;
-        let server_handle = tokio::spawn(async move {
-            let server = Server::new(port.clone());
-            server.run(server_shutdown_token).await
-        });
-
-        tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
 
         let client_handle = tokio::task::spawn_blocking(move || {
             let status = (|| -> synchronizer::Result<()> {
-                let mut map1: SMap<String, i32> = SMap::new(port.clone(), 1)?;
-                let mut map2: SMap<String, i32> = SMap::new(port.clone(), 1)?;
+                let mut map1: SMap<String, i32> = SMap::new(port, 1)?;
+                let mut map2: SMap<String, i32> = SMap::new(port, 1)?;
 
                 let foo = String::from("foo");
                 let bar = String::from("bar");
@@ -51,11 +42,9 @@ mod tests {
                 assert_eq!(map1.get(&bar), Some(8));
                 assert_eq!(map1.get(&dog), Some(7));
 
-                // Graceful shutdown of server.
-                shutdown_token.cancel();
                 Ok(())
             })();
-            if let Err(_) = status {
+            if status.is_err() {
                 panic!("Test failed!");
             }
         });
@@ -67,26 +56,18 @@ mod tests {
             }
         }
 
-        server_handle.await.unwrap();
+        server.shutdown().await;
     }
 
     #[tokio::test]
     async fn nested_structure() {
-        let shutdown_token = CancellationToken::new();
-        let server_shutdown_token = shutdown_token.clone();
-
-        let port = 7871;
-        let server_handle = tokio::spawn(async move {
-            let server = Server::new(port.clone());
-            server.run(server_shutdown_token).await
-        });
-
-        tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
 
         let client_handle = tokio::task::spawn_blocking(move || {
             let status = (|| -> synchronizer::Result<()> {
-                let mut map1: SMap<String, UMap<i32, i32>> = SMap::new(port.clone(), 1)?;
-                let mut map2: SMap<String, UMap<i32, i32>> = SMap::new(port.clone(), 1)?;
+                let mut map1: SMap<String, UMap<i32, i32>> = SMap::new(port, 1)?;
+                let mut map2: SMap<String, UMap<i32, i32>> = SMap::new(port, 1)?;
 
                 let foo = String::from("foo");
                 let bar = String::from("bar");
@@ -116,11 +97,9 @@ mod tests {
                 assert_eq!(map1.get_lock().get_ref(&bar).unwrap().get(&2).unwrap(), 11);
                 assert_eq!(map1.get_lock().get_ref(&dog).unwrap().get(&3).unwrap(), 12);
 
-                // Graceful shutdown of server.
-                shutdown_token.cancel();
                 Ok(())
             })();
-            if let Err(_) = status {
+            if status.is_err() {
                 panic!("Test failed!");
             }
         });
@@ -132,27 +111,20 @@ mod tests {
             }
         }
 
-        server_handle.await.unwrap();
+        server.shutdown().await;
     }
 
     #[tokio::test]
     async fn multiple_structures() {
-        let shutdown_token = CancellationToken::new();
-        let server_shutdown_token = shutdown_token.clone();
-        let port = 7872;
-        let server_handle = tokio::spawn(async move {
-            let server = Server::new(port.clone());
-            server.run(server_shutdown_token).await
-        });
-
-        tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
 
         let client_handle = tokio::task::spawn_blocking(move || {
             let status = (|| -> synchronizer::Result<()> {
-                let mut map1: SMap<String, i32> = SMap::new(port.clone(), 1)?;
-                let map2: SMap<String, i32> = SMap::new(port.clone(), 1)?;
-                let mut map3: SMap<String, i32> = SMap::new(port.clone(), 2)?;
-                let map4: SMap<String, i32> = SMap::new(port.clone(), 2)?;
+                let mut map1: SMap<String, i32> = SMap::new(port, 1)?;
+                let map2: SMap<String, i32> = SMap::new(port, 1)?;
+                let mut map3: SMap<String, i32> = SMap::new(port, 2)?;
+                let map4: SMap<String, i32> = SMap::new(port, 2)?;
 
                 let foo = String::from("foo");
                 let bar = String::from("bar");
@@ -176,10 +148,9 @@ mod tests {
                 assert_eq!(map4.get(&bar).unwrap(), 5);
                 assert_eq!(map4.get(&dog).unwrap(), 6);
 
-                shutdown_token.cancel();
                 Ok(())
             })();
-            if let Err(_) = status {
+            if status.is_err() {
                 panic!("Test failed!");
             }
         });
@@ -191,27 +162,19 @@ mod tests {
             }
         }
 
-        server_handle.await.unwrap();
+        server.shutdown().await;
     }
 
     #[tokio::test]
     async fn complex_operations_between_clients() {
-        let shutdown_token = CancellationToken::new();
-        let server_shutdown_token = shutdown_token.clone();
-
-        let port = 7873;
-        let server_handle = tokio::spawn(async move {
-            let server = Server::new(port.clone());
-            server.run(server_shutdown_token).await
-        });
-
-        tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
 
         let operations = 1000;
 
         let client1_handle = tokio::task::spawn_blocking(move || {
             let status = (|| -> synchronizer::Result<()> {
-                let mut client1: SMap<String, i32> = SMap::new(port.clone(), 1)?;
+                let mut client1: SMap<String, i32> = SMap::new(port, 1)?;
                 for i in 0..operations {
                     let key = format!("client1_key_{}", i);
                     client1.insert(key.clone(), i)?;
@@ -226,14 +189,14 @@ mod tests {
                 );
                 Ok(())
             })();
-            if let Err(_) = status {
+            if status.is_err() {
                 panic!("Complex test failed!");
             }
         });
 
         let client2_handle = tokio::task::spawn_blocking(move || {
             let status = (|| -> synchronizer::Result<()> {
-                let mut client2: SMap<String, i32> = SMap::new(port.clone(), 1)?;
+                let mut client2: SMap<String, i32> = SMap::new(port, 1)?;
                 for i in 0..operations {
                     let key = format!("client1_key_{}", i);
                     client2.insert(key.clone(), i * 2)?;
@@ -248,7 +211,7 @@ mod tests {
                 );
                 Ok(())
             })();
-            if let Err(_) = status {
+            if status.is_err() {
                 panic!("Complex test failed!");
             }
         });
@@ -263,7 +226,50 @@ mod tests {
         panic_if_error(client_result1);
         panic_if_error(client_result2);
 
-        shutdown_token.cancel();
-        server_handle.await.unwrap();
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn join_from_snapshot() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
+
+        let client_handle = tokio::task::spawn_blocking(move || {
+            let status = (|| -> synchronizer::Result<()> {
+                let mut map1: SMap<String, UMap<i32, i32>> = SMap::new(port, 1)?;
+
+                let foo = String::from("foo");
+                let bar = String::from("bar");
+
+                map1.insert(foo.clone(), UMap::new())?;
+                map1.get_mut(foo.clone()).insert(1, 5)?;
+                map1.insert(bar.clone(), UMap::new())?;
+
+                map1.sync()?;
+                map1.publish_snapshot()?;
+
+                map1.get_mut(bar.clone()).insert(2, 6)?;
+
+                let map2: SMap<String, UMap<i32, i32>> = SMap::new(port, 1)?;
+                map2.sync()?;
+
+                assert_eq!(map2.get_lock().get_ref(&foo).unwrap().get(&1).unwrap(), 5);
+                assert_eq!(map2.get_lock().get_ref(&bar).unwrap().get(&2).unwrap(), 6);
+
+                Ok(())
+            })();
+            if status.is_err() {
+                panic!("Test failed!");
+            }
+        });
+
+        match client_handle.await {
+            Ok(_) => println!("Blocking client test completed successfully."),
+            Err(e) => {
+                panic!("Blocking client test failed: {:?}", e)
+            }
+        }
+
+        server.shutdown().await;
     }
 }
@@ -0,0 +1,44 @@
+use shared_state_machine::communication::server::Server;
+use shared_state_machine::communication::synchronizer;
+use shared_state_machine::score::squeue::SQueue;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn work_queue() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
+
+        tokio::task::spawn_blocking(move || {
+            let status = (|| -> synchronizer::Result<()> {
+                let mut producer: SQueue<String> = SQueue::new(port, 1)?;
+                let mut consumer: SQueue<String> = SQueue::new(port, 1)?;
+
+                producer.push_back(String::from("first"))?;
+                producer.push_back(String::from("second"))?;
+                consumer.sync()?;
+                assert_eq!(consumer.len(), 2);
+                assert_eq!(consumer.front().as_deref(), Some("first"));
+                assert_eq!(consumer.back().as_deref(), Some("second"));
+
+                consumer.pop_front()?;
+                producer.sync()?;
+                assert_eq!(producer.front().as_deref(), Some("second"));
+
+                producer.push_front(String::from("urgent"))?;
+                consumer.sync()?;
+                assert_eq!(consumer.front().as_deref(), Some("urgent"));
+                Ok(())
+            })();
+            if let Err(e) = status {
+                panic!("{e}");
+            }
+        })
+        .await
+        .unwrap();
+
+        server.shutdown().await;
+    }
+}
@@ -0,0 +1,45 @@
+use shared_state_machine::communication::server::Server;
+use shared_state_machine::communication::synchronizer;
+use shared_state_machine::score::sset::SSet;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn shared_membership() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
+
+        tokio::task::spawn_blocking(move || {
+            let status = (|| -> synchronizer::Result<()> {
+                let mut workers: SSet<u32> = SSet::new(port, 1)?;
+                let mut other: SSet<u32> = SSet::new(port, 1)?;
+
+                workers.extend([1, 2, 3])?;
+                other.sync()?;
+                assert_eq!(other.len(), 3);
+
+                other.remove(2)?;
+                other.insert(4)?;
+                workers.sync()?;
+                assert!(!workers.contains(&2));
+                let mut members: Vec<_> = workers.get_lock().iter().copied().collect();
+                members.sort();
+                assert_eq!(members, vec![1, 3, 4]);
+
+                workers.clear()?;
+                other.sync()?;
+                assert!(other.is_empty());
+                Ok(())
+            })();
+            if let Err(e) = status {
+                panic!("{e}");
+            }
+        })
+        .await
+        .unwrap();
+
+        server.shutdown().await;
+    }
+}
@@ -3,7 +3,6 @@ use shared_state_machine::score::sstack::SStack;
 use shared_state_machine::ucore::ustack::UStack;
 use shared_state_machine::{communication::server::Server, ucore::umap::UMap};
 use std::{thread, time};
-use tokio_util::sync::CancellationToken;
 
 #[cfg(test)]
 mod tests {
@@ -12,21 +11,13 @@ mod tests {
 
     #[tokio::test]
     async fn nested_structure() {
-        let shutdown_token = CancellationToken::new();
-        let server_shutdown_token = shutdown_token.clone();
-
-        let port = 7850;
-        let server_handle = tokio::spawn(async move {
-            let server = Server::new(port.clone());
-            server.run(server_shutdown_token).await
-        });
-
-        tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
 
         let client_handle = tokio::task::spawn_blocking(move || {
             let status = (|| -> synchronizer::Result<()> {
-                let mut stc1: SStack<UMap<String, UStack<i32>>> = SStack::new(port.clone(), 1)?;
-                let mut stc2: SStack<UMap<String, UStack<i32>>> = SStack::new(port.clone(), 1)?;
+                let mut stc1: SStack<UMap<String, UStack<i32>>> = SStack::new(port, 1)?;
+                let mut stc2: SStack<UMap<String, UStack<i32>>> = SStack::new(port, 1)?;
 
                 let bar = String::from("bar");
 
@@ -145,11 +136,9 @@ mod tests {
                     4
                 );
 
-                // Graceful shutdown of server.
-                shutdown_token.cancel();
                 Ok(())
             })();
-            if let Err(_) = status {
+            if status.is_err() {
                 panic!("Test failed!");
             }
         });
@@ -161,6 +150,6 @@ mod tests {
             }
         }
 
-        server_handle.await.unwrap();
+        server.shutdown().await;
     }
 }
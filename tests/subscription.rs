@@ -0,0 +1,121 @@
+use futures::StreamExt;
+use shared_state_machine::communication::server::Server;
+use shared_state_machine::communication::subscription::Change;
+use shared_state_machine::communication::synchronizer;
+use shared_state_machine::score::async_smap::AsyncSMap;
+use shared_state_machine::score::smap::SMap;
+use shared_state_machine::score::svec::SVec;
+use shared_state_machine::ucore::umap::UMapUpdate;
+use std::sync::{Arc, Mutex};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn key_subscription_over_channel() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
+
+        tokio::task::spawn_blocking(move || {
+            let status = (|| -> synchronizer::Result<()> {
+                let mut map1: SMap<String, i32> = SMap::new(port, 1)?;
+                let map2: SMap<String, i32> = SMap::new(port, 1)?;
+                let changes = map2.subscribe().key(String::from("foo")).channel();
+
+                map1.insert(String::from("foo"), 1)?;
+                map1.insert(String::from("bar"), 2)?;
+                map1.remove(String::from("foo"))?;
+                map2.sync()?;
+
+                let changes: Vec<_> = changes.try_iter().collect();
+                assert_eq!(changes.len(), 2);
+                assert!(matches!(
+                    &changes[0],
+                    Change::Update { packet_id: 0, update: UMapUpdate::Insert(key, 1) } if key == "foo"
+                ));
+                assert!(matches!(
+                    &changes[1],
+                    Change::Update { packet_id: 2, update: UMapUpdate::Remove(key) } if key == "foo"
+                ));
+                Ok(())
+            })();
+            if status.is_err() {
+                panic!("Test failed!");
+            }
+        })
+        .await
+        .unwrap();
+
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn index_subscription_with_callback() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
+
+        tokio::task::spawn_blocking(move || {
+            let status = (|| -> synchronizer::Result<()> {
+                let mut svec: SVec<i32> = SVec::new(port, 1)?;
+                let packets = Arc::new(Mutex::new(vec![]));
+                let id = {
+                    let packets = packets.clone();
+                    svec.subscribe().index(1).callback(move |change| {
+                        if let Change::Update { packet_id, .. } = change {
+                            packets.lock().unwrap().push(packet_id);
+                        }
+                    })
+                };
+
+                svec.push(1)?; // 0: [1]
+                svec.push(2)?; // 1: [1, 2]
+                svec.push(3)?; // 2: [1, 2, 3]
+                svec.insert(0, 4)?; // 3: [4, 1, 2, 3]
+                svec.remove(3)?; // 4: [4, 1, 2]
+                svec.unsubscribe(id);
+                svec.pop()?;
+                svec.pop()?;
+
+                assert_eq!(*packets.lock().unwrap(), vec![1, 3]);
+                Ok(())
+            })();
+            if status.is_err() {
+                panic!("Test failed!");
+            }
+        })
+        .await
+        .unwrap();
+
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn subscription_stream() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
+
+        let status = async {
+            let mut map1: AsyncSMap<String, i32> = AsyncSMap::new(port, 1).await?;
+            let map2: AsyncSMap<String, i32> = AsyncSMap::new(port, 1).await?;
+            let mut changes = map2.subscribe().stream();
+
+            map1.insert(String::from("foo"), 1).await?;
+            map1.insert(String::from("bar"), 2).await?;
+
+            for expected in [0, 1] {
+                match changes.next().await {
+                    Some(Change::Update { packet_id, .. }) => assert_eq!(packet_id, expected),
+                    _ => panic!("Expected an update"),
+                }
+            }
+            synchronizer::Result::Ok(())
+        }
+        .await;
+        if status.is_err() {
+            panic!("Test failed!");
+        }
+
+        server.shutdown().await;
+    }
+}
@@ -3,7 +3,6 @@ use shared_state_machine::score::svec::SVec;
 use shared_state_machine::ucore::uvec::UVec;
 use shared_state_machine::{communication::server::Server, ucore::umap::UMap};
 use std::{thread, time};
-use tokio_util::sync::CancellationToken;
 
 #[cfg(test)]
 mod tests {
@@ -12,21 +11,13 @@ mod tests {
 
     #[tokio::test]
     async fn nested_structure() {
-        let shutdown_token = CancellationToken::new();
-        let server_shutdown_token = shutdown_token.clone();
-
-        let port = 7860;
-        let server_handle = tokio::spawn(async move {
-            let server = Server::new(port.clone());
-            server.run(server_shutdown_token).await
-        });
-
-        tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
 
         let client_handle = tokio::task::spawn_blocking(move || {
             let status = (|| -> synchronizer::Result<()> {
-                let mut vec1: SVec<UMap<String, UVec<i32>>> = SVec::new(port.clone(), 1)?;
-                let mut vec2: SVec<UMap<String, UVec<i32>>> = SVec::new(port.clone(), 1)?;
+                let mut vec1: SVec<UMap<String, UVec<i32>>> = SVec::new(port, 1)?;
+                let mut vec2: SVec<UMap<String, UVec<i32>>> = SVec::new(port, 1)?;
 
                 let bar = String::from("bar");
 
@@ -131,11 +122,9 @@ mod tests {
                     1
                 );
 
-                // Graceful shutdown of server.
-                shutdown_token.cancel();
                 Ok(())
             })();
-            if let Err(_) = status {
+            if status.is_err() {
                 panic!("Test failed!");
             }
         });
@@ -147,6 +136,6 @@ mod tests {
             }
         }
 
-        server_handle.await.unwrap();
+        server.shutdown().await;
     }
 }
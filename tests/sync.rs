@@ -0,0 +1,79 @@
+use shared_state_machine::communication::server::Server;
+use shared_state_machine::communication::synchronizer::{self, Delivery, SError};
+use shared_state_machine::score::async_smap::AsyncSMap;
+use shared_state_machine::score::smap::SMap;
+use shared_state_machine::score::svec::SVec;
+use std::time::Duration;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn sync_and_wait_for() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
+
+        tokio::task::spawn_blocking(move || {
+            let status = (|| -> synchronizer::Result<()> {
+                let mut map1: SMap<String, i32> = SMap::new(port, 1)?;
+                let map2: SMap<String, i32> = SMap::new(port, 1)?;
+                map1.insert(String::from("foo"), 1)?;
+                map1.insert(String::from("bar"), 2)?;
+                map2.sync()?;
+                assert_eq!(map2.version(), 2);
+                assert_eq!(map2.get(&String::from("bar")), Some(2));
+
+                let mut vec1: SVec<i32> = SVec::new(port, 2)?;
+                let vec2: SVec<i32> = SVec::new(port, 2)?;
+                let packet_id = match vec1.push(3)? {
+                    Delivery::Accepted(packet_id) => packet_id,
+                    Delivery::Queued(_) => panic!("Write wasn't accepted"),
+                };
+                vec2.wait_for(packet_id, Duration::from_secs(5))?;
+                assert_eq!(vec2.get(0), Some(3));
+                assert!(matches!(
+                    vec2.wait_for(packet_id + 1, Duration::from_millis(50)),
+                    Err(SError::TimeoutError(_))
+                ));
+                Ok(())
+            })();
+            if status.is_err() {
+                panic!("Test failed!");
+            }
+        })
+        .await
+        .unwrap();
+
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn async_sync_and_wait_for() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
+
+        let status = async {
+            let mut map1: AsyncSMap<String, i32> = AsyncSMap::new(port, 1).await?;
+            let mut map2: AsyncSMap<String, i32> = AsyncSMap::new(port, 1).await?;
+            map1.insert(String::from("foo"), 1).await?;
+            map2.sync().await?;
+            assert_eq!(map2.get(&String::from("foo")), Some(1));
+
+            map1.insert(String::from("bar"), 2).await?;
+            map2.wait_for(1, Duration::from_secs(5)).await?;
+            assert_eq!(map2.get(&String::from("bar")), Some(2));
+            assert!(matches!(
+                map2.wait_for(2, Duration::from_millis(50)).await,
+                Err(SError::TimeoutError(_))
+            ));
+            synchronizer::Result::Ok(())
+        }
+        .await;
+        if status.is_err() {
+            panic!("Test failed!");
+        }
+
+        server.shutdown().await;
+    }
+}
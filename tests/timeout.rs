@@ -0,0 +1,94 @@
+mod common;
+
+use shared_state_machine::communication::messages::ServerMessage;
+use shared_state_machine::communication::server::Server;
+use shared_state_machine::communication::synchronizer::{self, ReconnectPolicy, SError};
+use shared_state_machine::score::async_smap::AsyncSMap;
+use shared_state_machine::score::smap::SMap;
+use std::io::Read;
+use std::net::TcpListener;
+use std::thread;
+use std::time::Duration;
+
+/// Accepts one client's join request, then never answers again.
+fn silent_server() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut buffer = [0; 1024];
+        let _ = stream.read(&mut buffer);
+        common::write_message(&mut stream, &ServerMessage::Correct);
+        while stream.read(&mut buffer).is_ok_and(|read| read > 0) {}
+    });
+    port
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unanswered_requests_time_out() {
+        let status = (|| -> synchronizer::Result<()> {
+            let mut map: SMap<i32, i32> =
+                SMap::new(silent_server(), 1)?.with_timeout(Duration::from_millis(100));
+            assert!(matches!(map.insert(1, 1), Err(SError::TimeoutError(_))));
+            assert!(matches!(map.sync(), Err(SError::TimeoutError(_))));
+
+            let mut map: SMap<i32, i32> = SMap::new(silent_server(), 1)?;
+            let result = map.within(Duration::from_millis(100), |map| map.insert(1, 1));
+            assert!(matches!(result, Err(SError::TimeoutError(_))));
+            Ok(())
+        })();
+        if status.is_err() {
+            panic!("Test failed!");
+        }
+    }
+
+    #[tokio::test]
+    async fn async_requests_time_out() {
+        let status = async {
+            let mut map: AsyncSMap<i32, i32> = AsyncSMap::new(silent_server(), 1)
+                .await?
+                .with_timeout(Duration::from_millis(100));
+            assert!(matches!(
+                map.insert(1, 1).await,
+                Err(SError::TimeoutError(_))
+            ));
+            assert!(matches!(map.sync().await, Err(SError::TimeoutError(_))));
+            synchronizer::Result::Ok(())
+        }
+        .await;
+        if status.is_err() {
+            panic!("Test failed!");
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn writes_fail_once_disconnected() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
+        let map = tokio::task::spawn_blocking(move || {
+            SMap::<i32, i32>::with_reconnect_policy(port, 1, ReconnectPolicy::disabled())
+        })
+        .await
+        .unwrap();
+        let Ok(mut map) = map else {
+            panic!("Test failed!");
+        };
+        server.shutdown().await;
+
+        tokio::task::spawn_blocking(move || {
+            while map.connection_status() != synchronizer::ConnectionStatus::Failed {
+                thread::sleep(Duration::from_millis(10));
+            }
+            assert!(matches!(
+                map.insert(1, 1),
+                Err(SError::DisconnectedError(_))
+            ));
+        })
+        .await
+        .unwrap();
+    }
+}
@@ -0,0 +1,125 @@
+use shared_state_machine::communication::server::Server;
+use shared_state_machine::communication::subscription::Change;
+use shared_state_machine::communication::synchronizer::{self, Delivery, SError};
+use shared_state_machine::score::async_svec::AsyncSVec;
+use shared_state_machine::score::smap::SMap;
+use shared_state_machine::ucore::updateable::UpdateError;
+use shared_state_machine::ucore::uvec::UVec;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn transaction_is_one_packet() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
+
+        tokio::task::spawn_blocking(move || {
+            let status = (|| -> synchronizer::Result<()> {
+                let mut map1: SMap<String, UVec<i32>> = SMap::new(port, 1)?;
+                let map2: SMap<String, UVec<i32>> = SMap::new(port, 1)?;
+                let changes = map2.subscribe().channel();
+                map1.insert(String::from("foo"), UVec::new())?;
+
+                // Move the value from "foo" to "bar".
+                let mut transaction = map1.transaction();
+                transaction
+                    .remove(String::from("foo"))
+                    .insert(String::from("bar"), UVec::new())
+                    .get_mut(String::from("bar"))
+                    .push(1);
+                assert_eq!(transaction.len(), 3);
+                assert_eq!(map1.commit(transaction)?, Delivery::Accepted(1));
+                assert!(map1.get(&String::from("foo")).is_none());
+                assert_eq!(map1.get(&String::from("bar")).unwrap().get(0), Some(1));
+
+                map2.sync()?;
+                assert_eq!(map2.version(), 2);
+                assert!(map2.get(&String::from("foo")).is_none());
+                assert_eq!(map2.get(&String::from("bar")).unwrap().get(0), Some(1));
+                let packet_ids: Vec<_> = changes
+                    .try_iter()
+                    .filter_map(|change| match change {
+                        Change::Update { packet_id, .. } => Some(packet_id),
+                        Change::Replaced { .. } => None,
+                    })
+                    .collect();
+                assert_eq!(packet_ids, vec![0, 1, 1, 1]);
+
+                assert!(map1.commit(map1.transaction()).is_err());
+                Ok(())
+            })();
+            if status.is_err() {
+                panic!("Test failed!");
+            }
+        })
+        .await
+        .unwrap();
+
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn async_transaction() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
+
+        let status = async {
+            let mut svec: AsyncSVec<i32> = AsyncSVec::new(port, 1).await?;
+            svec.push(1).await?;
+            let mut transaction = svec.transaction();
+            transaction.remove(0).push(2).push(3);
+            assert_eq!(svec.commit(transaction).await?, Delivery::Accepted(1));
+
+            let mut other: AsyncSVec<i32> = AsyncSVec::new(port, 1).await?;
+            other.sync().await?;
+            assert_eq!(other.version(), 2);
+            assert_eq!(other.get(0), Some(2));
+            assert_eq!(other.get(1), Some(3));
+            synchronizer::Result::Ok(())
+        }
+        .await;
+        if status.is_err() {
+            panic!("Test failed!");
+        }
+
+        server.shutdown().await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn misfitting_transaction_is_not_applied() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
+
+        tokio::task::spawn_blocking(move || {
+            let status = (|| -> synchronizer::Result<()> {
+                let mut map: SMap<String, UVec<i32>> = SMap::new(port, 1)?;
+                let other: SMap<String, UVec<i32>> = SMap::new(port, 1)?;
+                let foo = String::from("foo");
+                let bar = String::from("bar");
+
+                let mut transaction = map.transaction();
+                transaction.insert(foo.clone(), UVec::new());
+                transaction.get_mut(bar.clone()).push(1);
+                assert!(matches!(
+                    map.commit(transaction),
+                    Err(SError::ApplyError(UpdateError::MissingKey))
+                ));
+                assert!(map.get(&foo).is_none());
+
+                other.sync()?;
+                assert!(other.get(&foo).is_none());
+                assert_eq!(other.take_update_failures().len(), 1);
+                Ok(())
+            })();
+            if let Err(error) = status {
+                panic!("Test failed: {}", error);
+            }
+        })
+        .await
+        .unwrap();
+
+        server.shutdown().await;
+    }
+}
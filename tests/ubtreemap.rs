@@ -0,0 +1,46 @@
+use shared_state_machine::ucore::ubtreemap::UBTreeMap;
+use shared_state_machine::ucore::updateable::Updatable;
+use shared_state_machine::ucore::uvec::UVec;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[allow(clippy::reversed_empty_ranges)] // Inverted ranges must be empty, not panic.
+    fn ordered_operations() {
+        let mut umap: UBTreeMap<u32, String> = UBTreeMap::new();
+        for (key, value) in [(30, "c"), (10, "a"), (20, "b"), (40, "d")] {
+            umap.apply_update(umap.insert(key, value.to_string()));
+        }
+        assert_eq!(umap.first_key_value(), Some((&10, &String::from("a"))));
+        assert_eq!(umap.last_key_value(), Some((&40, &String::from("d"))));
+        let keys: Vec<_> = umap.range(15..=30).map(|(key, _)| *key).collect();
+        assert_eq!(keys, vec![20, 30]);
+        assert_eq!(umap.range(30..20).count(), 0);
+
+        umap.apply_update(umap.remove_range(..25));
+        assert_eq!(
+            umap.iter().map(|(key, _)| *key).collect::<Vec<_>>(),
+            [30, 40]
+        );
+        umap.apply_update(umap.remove_range(40..30));
+        assert_eq!(umap.len(), 2);
+        umap.apply_update(umap.remove(30));
+        assert_eq!(umap.first_key_value(), Some((&40, &String::from("d"))));
+    }
+
+    #[test]
+    fn nested_operations() {
+        let mut umap: UBTreeMap<u32, UBTreeMap<u32, UVec<i32>>> = UBTreeMap::new();
+        umap.apply_update(umap.insert(1, UBTreeMap::new()));
+        umap.apply_update(umap.get_mut(1).insert(5, UVec::new()));
+        umap.apply_update(umap.get_mut(1).insert(6, UVec::new()));
+        umap.apply_update(umap.get_mut(1).get_mut(5).push(3));
+        umap.apply_update(umap.get_mut(1).remove_range(6..));
+
+        let inner = umap.get_ref(&1).unwrap();
+        assert_eq!(inner.len(), 1);
+        assert_eq!(inner.get_ref(&5).unwrap().get(0), Some(3));
+    }
+}
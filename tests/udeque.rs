@@ -0,0 +1,45 @@
+use shared_state_machine::ucore::udeque::UDeque;
+use shared_state_machine::ucore::updateable::{Updatable, UpdateError};
+use shared_state_machine::ucore::uvec::UVec;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simple_operations() {
+        let mut udeque: UDeque<i32> = UDeque::new();
+        udeque.apply_update(udeque.push_back(1));
+        udeque.apply_update(udeque.push_back(2));
+        udeque.apply_update(udeque.push_front(0));
+        assert_eq!(udeque.front(), Some(0));
+        assert_eq!(udeque.back(), Some(2));
+        assert_eq!(udeque.len(), 3);
+
+        udeque.apply_update(udeque.pop_front());
+        assert_eq!(udeque.front(), Some(1));
+        udeque.apply_update(udeque.pop_back());
+        assert_eq!(udeque.back(), Some(1));
+        assert_eq!(udeque.iter().copied().collect::<Vec<_>>(), vec![1]);
+
+        udeque.apply_update(udeque.pop_back());
+        assert!(udeque.is_empty());
+        assert_eq!(
+            udeque.try_apply_update(udeque.front_mut().add(1)),
+            Err(UpdateError::EmptyDeque)
+        );
+    }
+
+    #[test]
+    fn nested_operations() {
+        let mut udeque: UDeque<UDeque<UVec<i32>>> = UDeque::new();
+        udeque.apply_update(udeque.push_back(UDeque::new()));
+        udeque.apply_update(udeque.push_back(UDeque::new()));
+        udeque.apply_update(udeque.back_mut().push_front(UVec::new()));
+        udeque.apply_update(udeque.back_mut().front_mut().push(7));
+        udeque.apply_update(udeque.front_mut().push_back(UVec::new()));
+
+        assert_eq!(udeque.back_ref().unwrap().front().unwrap().get(0), Some(7));
+        assert!(udeque.front_ref().unwrap().back().unwrap().is_empty());
+    }
+}
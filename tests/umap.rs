@@ -32,21 +32,22 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::type_complexity)]
     fn recursive_operations() {
         let mut umap: UMap<String, UMap<i32, UMap<String, UMap<String, i32>>>> = UMap::new();
         let foo = String::from("foo");
         let bar = String::from("bar");
         let val = 7;
         umap.apply_update(umap.insert(foo.clone(), UMap::new()));
-        umap.apply_update(umap.get_mut(foo.clone()).insert(val.clone(), UMap::new()));
+        umap.apply_update(umap.get_mut(foo.clone()).insert(val, UMap::new()));
         umap.apply_update(
             umap.get_mut(foo.clone())
-                .get_mut(val.clone())
+                .get_mut(val)
                 .insert(bar.clone(), UMap::new()),
         );
         umap.apply_update(
             umap.get_mut(foo.clone())
-                .get_mut(val.clone())
+                .get_mut(val)
                 .get_mut(bar.clone())
                 .insert(foo.clone(), 5),
         );
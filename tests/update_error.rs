@@ -0,0 +1,80 @@
+use shared_state_machine::communication::server::Server;
+use shared_state_machine::communication::synchronizer::{self, SError};
+use shared_state_machine::score::smap::SMap;
+use shared_state_machine::ucore::umap::{UMap, UMapUpdate};
+use shared_state_machine::ucore::updateable::{Updatable, UpdateError};
+use shared_state_machine::ucore::ustack::{UStack, UStackUpdate};
+use shared_state_machine::ucore::uvec::{UVec, UVecUpdate};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn misfitting_updates_leave_the_state_unchanged() {
+        let mut uvec: UVec<i32> = UVec::new();
+        uvec.apply_update(uvec.push(1));
+        assert_eq!(
+            uvec.try_apply_update(UVecUpdate::Remove(3)),
+            Err(UpdateError::OutOfRange { index: 3, len: 1 })
+        );
+        assert_eq!(
+            uvec.try_apply_update(UVecUpdate::Insert(2, 5)),
+            Err(UpdateError::OutOfRange { index: 2, len: 1 })
+        );
+        assert_eq!(uvec.len(), 1);
+
+        let mut umap: UMap<i32, UVec<i32>> = UMap::new();
+        assert_eq!(
+            umap.try_apply_update(UMapUpdate::Nested(1, UVecUpdate::Push(1))),
+            Err(UpdateError::MissingKey)
+        );
+        umap.apply_update(umap.insert(1, UVec::new()));
+        assert_eq!(
+            umap.try_apply_update(UMapUpdate::Nested(1, UVecUpdate::Remove(0))),
+            Err(UpdateError::OutOfRange { index: 0, len: 0 })
+        );
+
+        let mut ustack: UStack<UVec<i32>> = UStack::new();
+        assert_eq!(
+            ustack.try_apply_update(UStackUpdate::Nested(UVecUpdate::Pop)),
+            Err(UpdateError::EmptyStack)
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn misfitting_updates_are_reported() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
+
+        tokio::task::spawn_blocking(move || {
+            let status = (|| -> synchronizer::Result<()> {
+                let mut map: SMap<i32, UVec<i32>> = SMap::new(port, 1)?;
+                let other: SMap<i32, UVec<i32>> = SMap::new(port, 1)?;
+                assert!(matches!(
+                    map.get_mut(1).push(5),
+                    Err(SError::ApplyError(UpdateError::MissingKey))
+                ));
+                assert!(map.take_update_failures().is_empty());
+
+                // Replicas keep applying the updates after the failed one.
+                map.insert(1, UVec::new())?;
+                map.get_mut(1).push(5)?;
+                other.sync()?;
+                assert_eq!(other.get(&1).map(|uvec| uvec.len()), Some(1));
+                let failures = other.take_update_failures();
+                assert_eq!(failures.len(), 1);
+                assert_eq!(failures[0].packet_id, 0);
+                assert_eq!(failures[0].error, UpdateError::MissingKey);
+                Ok(())
+            })();
+            if let Err(error) = status {
+                panic!("Test failed: {}", error);
+            }
+        })
+        .await
+        .unwrap();
+
+        server.shutdown().await;
+    }
+}
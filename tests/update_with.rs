@@ -0,0 +1,114 @@
+use shared_state_machine::communication::server::Server;
+use shared_state_machine::communication::synchronizer::{self, RetryPolicy, SError};
+use shared_state_machine::score::smap::SMap;
+use shared_state_machine::score::svec::SVec;
+use std::{panic, thread};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn concurrent_increments_are_not_lost() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
+
+        tokio::task::spawn_blocking(move || {
+            let status = (|| -> synchronizer::Result<()> {
+                let key = String::from("counter");
+                let writers: Vec<_> = (0..2)
+                    .map(|_| {
+                        let key = key.clone();
+                        thread::spawn(move || -> synchronizer::Result<()> {
+                            let mut map: SMap<String, i32> = SMap::new(port, 1)?;
+                            for _ in 0..20 {
+                                map.update_with(|state| {
+                                    let value = state.get(&key).unwrap_or(0);
+                                    Some(state.insert(key.clone(), value + 1))
+                                })?;
+                            }
+                            Ok(())
+                        })
+                    })
+                    .collect();
+                for writer in writers {
+                    writer.join().unwrap()?;
+                }
+
+                let map: SMap<String, i32> = SMap::new(port, 1)?;
+                map.sync()?;
+                assert_eq!(map.get(&key), Some(40));
+                Ok(())
+            })();
+            if status.is_err() {
+                panic!("Test failed!");
+            }
+        })
+        .await
+        .unwrap();
+
+        server.shutdown().await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn declined_and_contended_updates() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
+
+        tokio::task::spawn_blocking(move || {
+            let status = (|| -> synchronizer::Result<()> {
+                let mut svec: SVec<i32> =
+                    SVec::new(port, 1)?.with_retry_policy(RetryPolicy::new().max_retries(3));
+                // Nothing to remove.
+                let removed =
+                    svec.update_with(|state| (!state.is_empty()).then(|| state.remove(0)))?;
+                assert!(removed.is_none());
+                assert_eq!(svec.version(), 0);
+
+                // Another client writes every time the update is computed.
+                let mut other: SVec<i32> = SVec::new(port, 1)?;
+                let mut computed = 0;
+                let result = svec.update_with(|state| {
+                    computed += 1;
+                    other.push(0).ok()?;
+                    Some(state.push(1))
+                });
+                assert!(matches!(result, Err(SError::ContentionError(_))));
+                assert_eq!(computed, 4);
+                Ok(())
+            })();
+            if status.is_err() {
+                panic!("Test failed!");
+            }
+        })
+        .await
+        .unwrap();
+
+        server.shutdown().await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn panicking_update_fails_later_requests() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
+
+        tokio::task::spawn_blocking(move || {
+            let Ok(mut map) = SMap::<String, i32>::new(port, 1) else {
+                panic!("Test failed!");
+            };
+            let update = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+                let _ = map.update_with(|_| panic!("update failed"));
+            }));
+            assert!(update.is_err());
+            assert!(matches!(
+                map.insert(String::from("foo"), 1),
+                Err(SError::LockError(_))
+            ));
+            assert!(matches!(map.sync(), Err(SError::LockError(_))));
+        })
+        .await
+        .unwrap();
+
+        server.shutdown().await;
+    }
+}
@@ -0,0 +1,39 @@
+use shared_state_machine::ucore::umap::UMap;
+use shared_state_machine::ucore::updateable::Updatable;
+use shared_state_machine::ucore::uset::USet;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simple_operations() {
+        let mut uset: USet<i32> = USet::new();
+        uset.apply_update(uset.insert(5));
+        uset.apply_update(uset.insert(5));
+        uset.apply_update(uset.extend([1, 2, 3]));
+        assert_eq!(uset.len(), 4);
+        assert!(uset.contains(&5));
+
+        uset.apply_update(uset.remove(2));
+        let mut members: Vec<_> = uset.iter().copied().collect();
+        members.sort();
+        assert_eq!(members, vec![1, 3, 5]);
+
+        uset.apply_update(uset.clear());
+        assert!(uset.is_empty());
+    }
+
+    #[test]
+    fn nested_operations() {
+        let mut audiences: UMap<String, USet<u32>> = UMap::new();
+        let flag = String::from("beta");
+        audiences.apply_update(audiences.insert(flag.clone(), USet::new()));
+        audiences.apply_update(audiences.get_mut(flag.clone()).extend([7, 9]));
+        audiences.apply_update(audiences.get_mut(flag.clone()).remove(7));
+
+        let audience = audiences.get_ref(&flag).unwrap();
+        assert!(!audience.contains(&7));
+        assert!(audience.contains(&9));
+    }
+}
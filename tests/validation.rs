@@ -0,0 +1,188 @@
+mod common;
+
+use futures::prelude::*;
+use serde_json::json;
+use shared_state_machine::communication::messages::{
+    ClientMessage, Rejection, RejectionReason, ServerMessage,
+};
+use shared_state_machine::communication::server::Server;
+use shared_state_machine::communication::synchronizer::{self, SError};
+use shared_state_machine::communication::umessage::USnapshot;
+use shared_state_machine::communication::wal::LogConfig;
+use shared_state_machine::score::smap::SMap;
+use shared_state_machine::ucore::umap::UMap;
+use shared_state_machine::ucore::updateable::Updatable;
+use shared_state_machine::ucore::uvec::UVec;
+
+type Lists = UMap<i32, UVec<i32>>;
+
+fn is_invalid(result: synchronizer::Result<synchronizer::Delivery>) -> bool {
+    matches!(
+        result,
+        Err(SError::RejectedError(RejectionReason::InvalidUpdate))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn misfitting_updates_are_rejected() {
+        let server = Server::with_address("127.0.0.1:0")
+            .unwrap()
+            .with_validated_group::<Lists>(1)
+            .unwrap()
+            .start()
+            .await
+            .unwrap();
+        let port = server.local_addr().port();
+
+        tokio::task::spawn_blocking(move || {
+            let status = (|| -> synchronizer::Result<()> {
+                let mut map: SMap<i32, UVec<i32>> = SMap::new(port, 1)?;
+                assert!(is_invalid(map.get_mut(1).push(5)));
+                assert_eq!(map.version(), 0);
+
+                // A transaction is rejected as a whole.
+                let mut transaction = map.transaction();
+                transaction.insert(1, UVec::new());
+                transaction.get_mut(1).remove(0);
+                assert!(is_invalid(map.commit(transaction)));
+                assert_eq!(map.version(), 0);
+
+                map.insert(1, UVec::new())?;
+                map.get_mut(1).push(5)?;
+                assert!(is_invalid(map.get_mut(1).remove(1)));
+                assert_eq!(map.get(&1).map(|uvec| uvec.len()), Some(1));
+
+                // Other groups stay unvalidated.
+                let mut other: SMap<i32, UVec<i32>> = SMap::new(port, 2)?;
+                assert!(matches!(
+                    other.get_mut(1).push(5),
+                    Err(SError::ApplyError(_))
+                ));
+                Ok(())
+            })();
+            if let Err(error) = status {
+                panic!("Test failed: {}", error);
+            }
+        })
+        .await
+        .unwrap();
+
+        server.shutdown().await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn validation_resumes_from_the_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let start = |config: LogConfig| async move {
+            Server::with_address("127.0.0.1:0")
+                .unwrap()
+                .with_validated_group::<Lists>(1)
+                .unwrap()
+                .with_log(config)
+                .unwrap()
+                .start()
+                .await
+                .unwrap()
+        };
+
+        let server = start(LogConfig::new(dir.path())).await;
+        let port = server.local_addr().port();
+        tokio::task::spawn_blocking(move || {
+            let mut map: SMap<i32, UVec<i32>> = SMap::new(port, 1).ok().unwrap();
+            assert!(map.insert(1, UVec::new()).is_ok());
+        })
+        .await
+        .unwrap();
+        server.shutdown().await;
+
+        let server = start(LogConfig::new(dir.path())).await;
+        let port = server.local_addr().port();
+        tokio::task::spawn_blocking(move || {
+            let mut map: SMap<i32, UVec<i32>> = SMap::new(port, 1).ok().unwrap();
+            assert!(map.get_mut(1).push(5).is_ok());
+            assert!(is_invalid(map.get_mut(2).push(5)));
+        })
+        .await
+        .unwrap();
+        server.shutdown().await;
+    }
+    #[tokio::test(flavor = "multi_thread")]
+    async fn mismatching_snapshots_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let start = |config: LogConfig| async move {
+            Server::with_address("127.0.0.1:0")
+                .unwrap()
+                .with_validated_group::<Lists>(1)
+                .unwrap()
+                .with_log(config)
+                .unwrap()
+                .start()
+                .await
+                .unwrap()
+        };
+
+        let server = start(LogConfig::new(dir.path())).await;
+        let address = server.local_addr();
+        let port = address.port();
+        tokio::task::spawn_blocking(move || {
+            let mut map: SMap<i32, UVec<i32>> = SMap::new(port, 1).ok().unwrap();
+            assert!(map.insert(1, UVec::new()).is_ok());
+            assert!(map.get_mut(1).push(5).is_ok());
+        })
+        .await
+        .unwrap();
+
+        let (mut reader, mut writer) = common::connect(address).await;
+        writer
+            .send(json!(ClientMessage::JoinGroup(1, Some(2))))
+            .await
+            .unwrap();
+        let msg = reader.try_next().await.unwrap().unwrap();
+        assert_eq!(msg, json!(ServerMessage::Correct));
+
+        // Claims the state after both updates, but without the pushed value.
+        let mut lists: Lists = UMap::new();
+        lists.apply_update(lists.insert(1, UVec::new()));
+        let bogus = USnapshot::new(1, 2, &lists).unwrap();
+        writer
+            .send(json!(ClientMessage::Snapshot(bogus)))
+            .await
+            .unwrap();
+        let msg = reader.try_next().await.unwrap().unwrap();
+        assert_eq!(
+            msg,
+            json!(ServerMessage::Rejected(Rejection {
+                packet_id: 2,
+                head: 2,
+                reason: RejectionReason::InvalidSnapshot,
+            }))
+        );
+
+        lists.apply_update(lists.get_mut(1).push(5));
+        let snapshot = USnapshot::new(1, 2, &lists).unwrap();
+        writer
+            .send(json!(ClientMessage::Snapshot(snapshot)))
+            .await
+            .unwrap();
+        let msg = reader.try_next().await.unwrap().unwrap();
+        assert_eq!(msg, json!(ServerMessage::Accepted(2)));
+        drop((reader, writer));
+        server.shutdown().await;
+
+        // The group restarts validated from the accepted snapshot.
+        let server = start(LogConfig::new(dir.path())).await;
+        let port = server.local_addr().port();
+        tokio::task::spawn_blocking(move || {
+            let mut map: SMap<i32, UVec<i32>> = SMap::new(port, 1).ok().unwrap();
+            assert!(map.get_mut(1).remove(0).is_ok());
+            assert!(is_invalid(map.get_mut(1).remove(0)));
+        })
+        .await
+        .unwrap();
+        server.shutdown().await;
+    }
+}
@@ -0,0 +1,196 @@
+mod common;
+
+use common::start_server;
+use shared_state_machine::communication::messages::RejectionReason;
+use shared_state_machine::communication::synchronizer::{self, SError};
+use shared_state_machine::communication::umessage::{UMessage, USnapshot};
+use shared_state_machine::communication::wal::{FsyncPolicy, LogConfig, Wal};
+use shared_state_machine::score::smap::SMap;
+use shared_state_machine::ucore::umap::UMap;
+use shared_state_machine::ucore::updateable::Updatable;
+use shared_state_machine::ucore::ustack::UStack;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn state_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = LogConfig::new(dir.path()).fsync(FsyncPolicy::Batched(2));
+
+        let server = start_server("127.0.0.1:0", config.clone()).await;
+        let address = server.local_addr();
+        let port = address.port();
+
+        tokio::task::spawn_blocking(move || {
+            let status = (|| -> synchronizer::Result<()> {
+                let mut map: SMap<String, i32> = SMap::new(port, 1)?;
+                map.insert(String::from("foo"), 1)?;
+                map.insert(String::from("bar"), 2)?;
+                map.remove(String::from("foo"))?;
+                Ok(())
+            })();
+            if status.is_err() {
+                panic!("Test failed!");
+            }
+        })
+        .await
+        .unwrap();
+
+        server.shutdown().await;
+
+        let server = start_server("127.0.0.1:0", config).await;
+        let port = server.local_addr().port();
+
+        tokio::task::spawn_blocking(move || {
+            let status = (|| -> synchronizer::Result<()> {
+                let mut map: SMap<String, i32> = SMap::new(port, 1)?;
+                map.sync()?;
+                assert_eq!(map.get(&String::from("foo")), None);
+                assert_eq!(map.get(&String::from("bar")), Some(2));
+
+                // Packet numbering continues after the restored history.
+                map.insert(String::from("dog"), 3)?;
+                Ok(())
+            })();
+            if status.is_err() {
+                panic!("Test failed!");
+            }
+        })
+        .await
+        .unwrap();
+
+        server.shutdown().await;
+    }
+
+    #[test]
+    fn torn_tail_is_discarded() {
+        let dir = tempfile::tempdir().unwrap();
+        // Every record starts a new segment.
+        let config = LogConfig::new(dir.path()).segment_size(1);
+        let ustack: UStack<i32> = UStack::new();
+
+        {
+            let wal = Wal::open(config.clone()).unwrap();
+            let mut log = wal.create_group(3).unwrap();
+            for packet_id in 0..3 {
+                log.append(&UMessage::new(3, packet_id, &ustack.push(5)).unwrap())
+                    .unwrap();
+            }
+        }
+
+        let group_dir = dir.path().join("group-3");
+        let mut segments: Vec<_> = fs::read_dir(&group_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        segments.sort();
+        assert_eq!(segments.len(), 3);
+
+        // Simulate a crash in the middle of writing a record.
+        let mut last = OpenOptions::new()
+            .append(true)
+            .open(segments.last().unwrap())
+            .unwrap();
+        last.write_all(&[42, 0, 0, 0, 1, 2]).unwrap();
+        drop(last);
+
+        let wal = Wal::open(config.clone()).unwrap();
+        let mut recovered = wal.recover().unwrap();
+        assert_eq!(recovered.len(), 1);
+        let mut group = recovered.pop().unwrap();
+        assert_eq!(group.group_id, 3);
+        assert_eq!(group.history.len(), 3);
+        assert_eq!(group.history[2].packet_id, 2);
+
+        group
+            .log
+            .append(&UMessage::new(3, 3, &ustack.pop()).unwrap())
+            .unwrap();
+        drop(group);
+
+        let recovered = Wal::open(config).unwrap().recover().unwrap();
+        assert_eq!(recovered[0].history.len(), 4);
+    }
+
+    #[test]
+    fn snapshot_compacts_segments() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = LogConfig::new(dir.path()).segment_size(1);
+        let mut ustack: UStack<i32> = UStack::new();
+
+        {
+            let wal = Wal::open(config.clone()).unwrap();
+            let mut log = wal.create_group(1).unwrap();
+            for packet_id in 0..5 {
+                log.append(&UMessage::new(1, packet_id, &ustack.push(5)).unwrap())
+                    .unwrap();
+            }
+            ustack.apply_update(ustack.push(5));
+            ustack.apply_update(ustack.push(5));
+            ustack.apply_update(ustack.push(5));
+            log.write_snapshot(&USnapshot::new(1, 3, &ustack).unwrap())
+                .unwrap();
+        }
+
+        let segments = fs::read_dir(dir.path().join("group-1"))
+            .unwrap()
+            .filter(|entry| entry.as_ref().unwrap().path().extension().unwrap() == "seg")
+            .count();
+        assert_eq!(segments, 2);
+
+        let recovered = Wal::open(config).unwrap().recover().unwrap();
+        let snapshot = recovered[0].snapshot.as_ref().unwrap();
+        assert_eq!(snapshot.packet_id, 3);
+        assert_eq!(snapshot.get_state::<UStack<i32>>().unwrap().top(), Some(5));
+        let history: Vec<_> = recovered[0]
+            .history
+            .iter()
+            .map(|umessage| umessage.packet_id)
+            .collect();
+        assert_eq!(history, vec![3, 4]);
+    }
+    #[tokio::test(flavor = "multi_thread")]
+    async fn group_rejects_updates_past_the_last_packet_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = LogConfig::new(dir.path());
+        {
+            let wal = Wal::open(config.clone()).unwrap();
+            let mut log = wal.create_group(1).unwrap();
+            let umap: UMap<String, i32> = UMap::new();
+            log.write_snapshot(&USnapshot::new(1, u32::MAX - 1, &umap).unwrap())
+                .unwrap();
+        }
+
+        let server = start_server("127.0.0.1:0", config).await;
+        let port = server.local_addr().port();
+
+        tokio::task::spawn_blocking(move || {
+            let status = (|| -> synchronizer::Result<()> {
+                let mut map: SMap<String, i32> = SMap::new(port, 1)?;
+                map.sync()?;
+                assert_eq!(map.version(), u32::MAX - 1);
+                map.insert(String::from("foo"), 1)?;
+                assert_eq!(map.version(), u32::MAX);
+                assert!(matches!(
+                    map.insert(String::from("bar"), 2),
+                    Err(SError::RejectedError(RejectionReason::PacketIdsExhausted))
+                ));
+                map.sync()?;
+                assert_eq!(map.get(&String::from("foo")), Some(1));
+                assert_eq!(map.get(&String::from("bar")), None);
+                Ok(())
+            })();
+            if let Err(error) = status {
+                panic!("Test failed: {}", error);
+            }
+        })
+        .await
+        .unwrap();
+
+        server.shutdown().await;
+    }
+}
@@ -0,0 +1,147 @@
+use shared_state_machine::communication::server::Server;
+use shared_state_machine::communication::subscription::Change;
+use shared_state_machine::communication::synchronizer;
+use shared_state_machine::score::smap::SMap;
+use shared_state_machine::score::squeue::SQueue;
+use shared_state_machine::score::sset::SSet;
+use shared_state_machine::score::sstack::SStack;
+use shared_state_machine::ucore::uvec::UVec;
+use std::sync::mpsc::Receiver;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet_ids<U>(changes: Receiver<Change<U>>) -> Vec<u32> {
+        changes
+            .try_iter()
+            .filter_map(|change| match change {
+                Change::Update { packet_id, .. } => Some(packet_id),
+                Change::Replaced { .. } => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn watch_nested_path() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
+
+        tokio::task::spawn_blocking(move || {
+            let status = (|| -> synchronizer::Result<()> {
+                let mut map: SMap<String, UVec<i32>> = SMap::new(port, 1)?;
+                let foo = String::from("foo");
+                let bar = String::from("bar");
+                let changes = map.watch().key(foo.clone()).index(1).channel();
+
+                map.insert(foo.clone(), UVec::new())?; // 0: foo = []
+                map.get_mut(foo.clone()).push(1)?; // 1: foo = [1]
+                map.get_mut(foo.clone()).push(2)?; // 2: foo = [1, 2]
+                map.insert(bar.clone(), UVec::new())?; // 3
+                map.get_mut(bar.clone()).push(5)?; // 4
+                map.get_mut(foo.clone()).insert(0, 9)?; // 5: foo = [9, 1, 2]
+                map.get_mut(foo.clone()).push(3)?; // 6: foo = [9, 1, 2, 3]
+
+                assert_eq!(packet_ids(changes), vec![0, 2, 5]);
+                Ok(())
+            })();
+            if status.is_err() {
+                panic!("Test failed!");
+            }
+        })
+        .await
+        .unwrap();
+
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn watch_stack_top() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
+
+        tokio::task::spawn_blocking(move || {
+            let status = (|| -> synchronizer::Result<()> {
+                let mut stack: SStack<UVec<i32>> = SStack::new(port, 1)?;
+                let changes = stack.watch().top().index(0).channel();
+
+                stack.push(UVec::new())?; // 0: top = []
+                stack.top_mut().push(1)?; // 1: top = [1]
+                stack.top_mut().push(2)?; // 2: top = [1, 2]
+                stack.pop()?; // 3
+
+                assert_eq!(packet_ids(changes), vec![0, 1, 3]);
+                Ok(())
+            })();
+            if status.is_err() {
+                panic!("Test failed!");
+            }
+        })
+        .await
+        .unwrap();
+
+        server.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn watch_queue_back() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
+
+        tokio::task::spawn_blocking(move || {
+            let status = (|| -> synchronizer::Result<()> {
+                let mut queue: SQueue<i32> = SQueue::new(port, 1)?;
+                let changes = queue.watch().back().channel();
+
+                queue.push_back(1)?; // 0: [1]
+                queue.push_front(0)?; // 1: [0, 1]
+                queue.push_back(2)?; // 2: [0, 1, 2]
+                queue.pop_front()?; // 3: [1, 2]
+                queue.front_mut().add(5)?; // 4: [6, 2]
+                queue.pop_back()?; // 5: [6]
+                queue.front_mut().add(1)?; // 6: [7]
+
+                assert_eq!(packet_ids(changes), vec![0, 2, 5, 6]);
+                Ok(())
+            })();
+            if status.is_err() {
+                panic!("Test failed!");
+            }
+        })
+        .await
+        .unwrap();
+
+        server.shutdown().await;
+    }
+    #[tokio::test]
+    async fn watch_set_membership() {
+        let server = Server::bind("127.0.0.1:0").await.unwrap();
+        let port = server.local_addr().port();
+
+        tokio::task::spawn_blocking(move || {
+            let status = (|| -> synchronizer::Result<()> {
+                let mut set: SSet<i32> = SSet::new(port, 1)?;
+                let changes = set.watch_contains(1).channel();
+
+                set.insert(1)?; // 0: {1}
+                set.insert(1)?; // 1: {1}
+                set.insert(2)?; // 2: {1, 2}
+                set.remove(1)?; // 3: {2}
+                set.extend(vec![1, 3])?; // 4: {1, 2, 3}
+                set.remove(2)?; // 5: {1, 3}
+                set.clear()?; // 6: {}
+                set.clear()?; // 7: {}
+
+                assert_eq!(packet_ids(changes), vec![0, 3, 4, 6]);
+                Ok(())
+            })();
+            if status.is_err() {
+                panic!("Test failed!");
+            }
+        })
+        .await
+        .unwrap();
+
+        server.shutdown().await;
+    }
+}